@@ -4,6 +4,13 @@ use arcis_imports::*;
 mod circuits {
     use arcis_imports::*;
 
+    /// Order fields encrypted by the trader's client
+    pub struct OrderInput {
+        pub price: u64,
+        pub quantity: u64,
+        pub side: bool,
+    }
+
     #[derive(Copy, Clone)]
     pub struct Order {
         pub order_id: u128,
@@ -11,7 +18,16 @@ mod circuits {
         pub quantity: u64,
         pub side: bool,
         pub trader_id: u128,
+        /// Unix time the order was submitted
         pub timestamp: u64,
+        /// Slot the order was stamped with, shared by an auction epoch's
+        /// orders; decides which match may fill it and which side is the taker
+        pub slot: u64,
+        /// Tokens escrowed on-chain when the order was submitted
+        pub escrow: u64,
+        /// Whether the trader had a referrer when the order was submitted;
+        /// only then is a referral share carved out of its taker fees
+        pub has_referrer: bool,
     }
 
     pub struct OrderBook {
@@ -31,10 +47,139 @@ mod circuits {
         pub timestamp: u64,
     }
 
-    pub struct MatchResult {
+    /// Result of a submission, encrypted to the trader's registered key
+    pub struct OrderReceipt {
+        pub order_id: u128,
+        pub accepted: bool,
+        /// REJECT_* code explaining a rejection, REJECT_NONE when accepted
+        pub reason: u8,
+    }
+
+    pub const REJECT_NONE: u8 = 0;
+    pub const REJECT_ZERO_PRICE: u8 = 1;
+    pub const REJECT_ZERO_QUANTITY: u8 = 2;
+    pub const REJECT_PRICE_OUT_OF_RANGE: u8 = 3;
+    pub const REJECT_QUANTITY_OUT_OF_RANGE: u8 = 4;
+    pub const REJECT_OFF_TICK: u8 = 5;
+    pub const REJECT_OFF_LOT: u8 = 6;
+    pub const REJECT_BOOK_FULL: u8 = 7;
+    /// Escrow was taken in the mint the order does not pay with
+    pub const REJECT_ESCROW_SIDE: u8 = 8;
+    /// Price times quantity leaves no room in a u64 for the highest fee
+    pub const REJECT_NOTIONAL_TOO_LARGE: u8 = 9;
+
+    /// One trader's fills from a match, encrypted to their registered key
+    pub struct FillReport {
+        pub trades: [Trade; 5],
+        pub trade_count: u8,
+    }
+
+    /// One of a trader's resting orders as shown back to them
+    #[derive(Copy, Clone)]
+    pub struct OpenOrder {
+        pub order_id: u128,
+        pub price: u64,
+        pub quantity: u64,
+        pub side: bool,
+    }
+
+    /// A page of a trader's resting orders, encrypted to their registered key
+    pub struct MyOrders {
+        pub orders: [OpenOrder; 5],
+        pub count: u8,
+        /// Resting orders the trader has across all pages
+        pub total: u8,
+    }
+
+    /// Resting size in price bands either side of mid, published to market makers
+    pub struct DepthSnapshot {
+        pub mid_price: u64,
+        pub bid_depth: [u64; 4],
+        pub ask_depth: [u64; 4],
+    }
+
+    /// Would-be clearing price of the book; quantities are not revealed
+    pub struct IndicativePrice {
+        pub price: u64,
+        pub crossed: bool,
+        /// 0 when balanced, 1 when buy size exceeds sell size, 2 otherwise
+        pub imbalance_side: u8,
+    }
+
+    /// Trade prints of one match, kept MXE-encrypted on the tape until the
+    /// pair's transparency delay has passed
+    pub struct TapeBatch {
+        pub prices: [u64; 5],
+        pub quantities: [u64; 5],
+        pub timestamps: [u64; 5],
+        pub trade_count: u8,
+    }
+
+    /// Trade terms published under a disclosing policy; counterparties are left out
+    pub struct TradeDisclosure {
+        pub prices: [u64; 5],
+        pub quantities: [u64; 5],
+        pub trade_count: u8,
+    }
+
+    /// Net vault movement of one trader in a match, applied to their user account
+    #[derive(Copy, Clone)]
+    pub struct Settlement {
+        pub base_credit: u64,
+        pub quote_credit: u64,
+        pub base_debit: u64,
+        pub quote_debit: u64,
+        /// Escrow left on orders that were fully filled and dropped from the book
+        pub base_release: u64,
+        pub quote_release: u64,
+        pub fill_count: u8,
+        pub orders_closed: u8,
+        /// Fees on the trader's buys, paid out of quote escrow
+        pub buy_fee: u64,
+        /// Fees on the trader's sells, withheld from quote proceeds
+        pub sell_fee: u64,
+        /// Maker rebates credited to the trader out of taker fees
+        pub rebate: u64,
+        /// Part of the trader's taker fees owed to their referrer, if any
+        pub referral: u64,
+        /// Most the trader's tier discount on these fees can be, at the
+        /// highest tier's rate; held back from the fee sweep until they claim
+        pub credit_reserve: u64,
+    }
+
+    /// A trader's private fee state: cumulative quote volume, which selects
+    /// their fee tier, and the tier discounts they have earned but not claimed
+    #[derive(Copy, Clone)]
+    pub struct TraderVolume {
+        pub volume: u64,
+        pub fee_credit: u64,
+    }
+
+    /// Fills of one match, kept MXE-encrypted until each trader in them has
+    /// settled their share. Fees are at the pair's base rates.
+    pub struct MatchFills {
         pub trades: [Trade; 5],
         pub trade_count: u8,
-        pub order_book: OrderBook,
+        pub buyer_fees: [u64; 5],
+        pub seller_fees: [u64; 5],
+        /// Maker rebate of each fill, paid out of the taker's fee
+        pub rebates: [u64; 5],
+        /// Referrer share of each fill's taker fee
+        pub referrals: [u64; 5],
+        /// Escrow left on the buy order a fill closed
+        pub buyer_releases: [u64; 5],
+        /// Escrow left on the sell order a fill closed
+        pub seller_releases: [u64; 5],
+        pub buyer_is_taker: [bool; 5],
+        pub buyer_closed: [bool; 5],
+        pub seller_closed: [bool; 5],
+    }
+
+    /// What a match reveals about who traded: the traders in its fills,
+    /// sorted by ID with repeats and unused slots zeroed
+    pub struct MatchSummary {
+        pub trade_count: u8,
+        pub participants: [u128; 10],
     }
 
     impl Order {
@@ -46,6 +191,9 @@ mod circuits {
                 side: false,
                 trader_id: 0,
                 timestamp: 0,
+                slot: 0,
+                escrow: 0,
+                has_referrer: false,
             }
         }
     }
@@ -62,6 +210,102 @@ mod circuits {
         }
     }
 
+    impl FillReport {
+        pub fn new() -> Self {
+            FillReport {
+                trades: [Trade::new(); 5],
+                trade_count: 0,
+            }
+        }
+    }
+
+    impl OpenOrder {
+        pub fn new() -> Self {
+            OpenOrder {
+                order_id: 0,
+                price: 0,
+                quantity: 0,
+                side: false,
+            }
+        }
+    }
+
+    impl MyOrders {
+        pub fn new() -> Self {
+            MyOrders {
+                orders: [OpenOrder::new(); 5],
+                count: 0,
+                total: 0,
+            }
+        }
+
+        /// Count one of the trader's orders, keeping it if it falls on the page
+        /// starting at `first`. Slots are picked with constant indices since
+        /// the running total depends on secret ownership.
+        fn collect(&mut self, order: &Order, first: u8) {
+            for slot in 0..5 {
+                if self.total == first + slot {
+                    self.orders[slot as usize] = OpenOrder {
+                        order_id: order.order_id,
+                        price: order.price,
+                        quantity: order.quantity,
+                        side: order.side,
+                    };
+                    self.count += 1;
+                }
+            }
+            self.total += 1;
+        }
+    }
+
+    impl TradeDisclosure {
+        pub fn new() -> Self {
+            TradeDisclosure {
+                prices: [0; 5],
+                quantities: [0; 5],
+                trade_count: 0,
+            }
+        }
+    }
+
+    impl Settlement {
+        pub fn new() -> Self {
+            Settlement {
+                base_credit: 0,
+                quote_credit: 0,
+                base_debit: 0,
+                quote_debit: 0,
+                base_release: 0,
+                quote_release: 0,
+                fill_count: 0,
+                orders_closed: 0,
+                buy_fee: 0,
+                sell_fee: 0,
+                rebate: 0,
+                referral: 0,
+                credit_reserve: 0,
+            }
+        }
+    }
+
+    impl MatchFills {
+        pub fn new() -> Self {
+            MatchFills {
+                trades: [Trade::new(); 5],
+                trade_count: 0,
+                buyer_fees: [0; 5],
+                seller_fees: [0; 5],
+                rebates: [0; 5],
+                referrals: [0; 5],
+                buyer_releases: [0; 5],
+                seller_releases: [0; 5],
+                buyer_is_taker: [false; 5],
+                buyer_closed: [false; 5],
+                seller_closed: [false; 5],
+            }
+        }
+    }
+
     impl OrderBook {
         pub fn new() -> Self {
             OrderBook {
@@ -97,8 +341,9 @@ mod circuits {
             can_add
         }
 
-        fn cancel_order(&mut self, order_id: u128, trader_id: u128) -> bool {
+        fn cancel_order(&mut self, order_id: u128, trader_id: u128) -> (bool, Order) {
             let mut found = false;
+            let mut cancelled = Order::new();
 
             // Try to find and remove the order from buy orders
             // Must use constant loop bounds for MPC compilation
@@ -108,6 +353,7 @@ mod circuits {
                 let is_target = self.buy_orders[idx].order_id == order_id && self.buy_orders[idx].trader_id == trader_id;
 
                 if order_exists && is_target && !found {
+                    cancelled = self.buy_orders[idx];
                     // Shift remaining orders left to fill the gap
                     for j in i..9 {
                         let j_idx = j as usize;
@@ -125,6 +371,7 @@ mod circuits {
                 let is_target = self.sell_orders[idx].order_id == order_id && self.sell_orders[idx].trader_id == trader_id;
 
                 if order_exists && is_target && !found {
+                    cancelled = self.sell_orders[idx];
                     // Shift remaining orders left to fill the gap
                     for j in i..9 {
                         let j_idx = j as usize;
@@ -135,10 +382,50 @@ mod circuits {
                 }
             }
 
-            found
+            (found, cancelled)
         }
     }
 
+    /// First rule an order breaks against the market parameters, in the order
+    /// the REJECT_* codes are listed
+    fn validate_order(
+        input: &OrderInput,
+        tick_size: u64,
+        lot_size: u64,
+        min_quantity: u64,
+        max_price: u64,
+        max_quantity: u64,
+        price_scale: u64,
+        max_notional: u64,
+    ) -> u8 {
+        let mut reason = REJECT_NONE;
+        if input.price == 0 {
+            reason = REJECT_ZERO_PRICE;
+        }
+        if reason == REJECT_NONE && input.quantity == 0 {
+            reason = REJECT_ZERO_QUANTITY;
+        }
+        if reason == REJECT_NONE && input.price > max_price {
+            reason = REJECT_PRICE_OUT_OF_RANGE;
+        }
+        if reason == REJECT_NONE && (input.quantity < min_quantity || input.quantity > max_quantity)
+        {
+            reason = REJECT_QUANTITY_OUT_OF_RANGE;
+        }
+        if reason == REJECT_NONE && input.price % tick_size != 0 {
+            reason = REJECT_OFF_TICK;
+        }
+        if reason == REJECT_NONE && input.quantity % lot_size != 0 {
+            reason = REJECT_OFF_LOT;
+        }
+        if reason == REJECT_NONE
+            && notional(input.price, input.quantity, price_scale, true) > max_notional as u128
+        {
+            reason = REJECT_NOTIONAL_TOO_LARGE;
+        }
+        reason
+    }
+
     /// Initialize an empty order book
     #[instruction]
     pub fn init_order_book(mxe: Mxe) -> Enc<Mxe, OrderBook> {
@@ -146,36 +433,428 @@ mod circuits {
         mxe.from_arcis(order_book)
     }
 
+    /// Every book-mutating instruction echoes the book version it was computed
+    /// against so the program can reject results built on a stale book.
+    /// The trader ID is supplied by the program from the signer. Orders that
+    /// break the plaintext market parameters or don't fit in the book are
+    /// rejected; acceptance is revealed so a rejected order's escrow can be
+    /// released, while the reason only goes into the receipt. Escrow must be
+    /// in the mint the order pays with: quote for a buy, base for a sell.
+    /// The assigned order ID goes back to the trader's registered key, and
+    /// the full order to the pair's auditor when `audit` is set.
     #[instruction]
     pub fn submit_order(
-        order_ctxt: Enc<Shared, Order>,
+        order_ctxt: Enc<Shared, OrderInput>,
+        trader_id: u128,
+        timestamp: u64,
+        slot: u64,
+        escrow: u64,
+        escrow_is_base: bool,
+        has_referrer: bool,
+        tick_size: u64,
+        lot_size: u64,
+        min_quantity: u64,
+        max_price: u64,
+        max_quantity: u64,
+        price_scale: u64,
+        max_notional: u64,
         book_ctxt: Enc<Mxe, OrderBook>,
-    ) -> Enc<Mxe, OrderBook> {
-        let order = order_ctxt.to_arcis();
+        book_version: u64,
+        receiver: Shared,
+        audit: bool,
+        auditor: Shared,
+    ) -> (
+        Enc<Mxe, OrderBook>,
+        u64,
+        bool,
+        Enc<Shared, OrderReceipt>,
+        Enc<Shared, Order>,
+    ) {
+        let input = order_ctxt.to_arcis();
         let mut book = book_ctxt.to_arcis();
+        let order_id = book.next_order_id;
 
-        let _success = if order.side {
-            book.add_buy_order(order)
-        } else {
-            book.add_sell_order(order)
+        let order = Order {
+            order_id: 0,
+            price: input.price,
+            quantity: input.quantity,
+            side: input.side,
+            trader_id,
+            timestamp,
+            slot,
+            escrow,
+            has_referrer,
+        };
+
+        let mut reason = validate_order(
+            &input,
+            tick_size,
+            lot_size,
+            min_quantity,
+            max_price,
+            max_quantity,
+            price_scale,
+            max_notional,
+        );
+        if reason == REJECT_NONE && escrow > 0 && input.side == escrow_is_base {
+            reason = REJECT_ESCROW_SIDE;
+        }
+        let mut accepted = false;
+        if reason == REJECT_NONE {
+            accepted = if order.side {
+                book.add_buy_order(order)
+            } else {
+                book.add_sell_order(order)
+            };
+            if !accepted {
+                reason = REJECT_BOOK_FULL;
+            }
+        }
+
+        let receipt = OrderReceipt {
+            order_id,
+            accepted,
+            reason,
         };
+        let mut audit_record = Order::new();
+        if audit {
+            audit_record = order;
+            audit_record.order_id = order_id;
+        }
 
-        book_ctxt.owner.from_arcis(book)
+        (
+            book_ctxt.owner.from_arcis(book),
+            book_version.reveal(),
+            accepted.reveal(),
+            receiver.from_arcis(receipt),
+            auditor.from_arcis(audit_record),
+        )
     }
 
+    /// Cancellation reveals whether an order was removed along with its escrow
+    /// and side, so the program can release the trader's locked funds. The
+    /// removed order goes to the pair's auditor when `audit` is set.
     #[instruction]
     pub fn cancel_order(
         order_id: Enc<Shared, u128>,
-        trader_id: Enc<Shared, u128>,
+        trader_id: u128,
         book_ctxt: Enc<Mxe, OrderBook>,
-    ) -> Enc<Mxe, OrderBook> {
+        book_version: u64,
+        audit: bool,
+        auditor: Shared,
+    ) -> (Enc<Mxe, OrderBook>, u64, bool, u64, bool, Enc<Shared, Order>) {
         let order_id_val = order_id.to_arcis();
-        let trader_id_val = trader_id.to_arcis();
         let mut book = book_ctxt.to_arcis();
 
-        let _cancelled = book.cancel_order(order_id_val, trader_id_val);
+        let (cancelled, order) = book.cancel_order(order_id_val, trader_id);
+        let audit_record = if audit { order } else { Order::new() };
+
+        (
+            book_ctxt.owner.from_arcis(book),
+            book_version.reveal(),
+            cancelled.reveal(),
+            order.escrow.reveal(),
+            order.side.reveal(),
+            auditor.from_arcis(audit_record),
+        )
+    }
+
+    /// Read-only view of the caller's resting orders. The book is not
+    /// re-encrypted, so the version is only echoed for the program to check
+    /// the book was not rewritten while the query ran. Orders are numbered
+    /// buys first, then sells, and `page` selects five of them.
+    #[instruction]
+    pub fn query_my_orders(
+        trader_id: u128,
+        page: u8,
+        book_ctxt: Enc<Mxe, OrderBook>,
+        book_version: u64,
+        receiver: Shared,
+    ) -> (u64, Enc<Shared, MyOrders>) {
+        let book = book_ctxt.to_arcis();
+        let first = page * 5;
+        let mut result = MyOrders::new();
+
+        for i in 0..10 {
+            let order = book.buy_orders[i as usize];
+            if i < book.buy_count && order.trader_id == trader_id {
+                result.collect(&order, first);
+            }
+        }
+        for i in 0..10 {
+            let order = book.sell_orders[i as usize];
+            if i < book.sell_count && order.trader_id == trader_id {
+                result.collect(&order, first);
+            }
+        }
 
-        book_ctxt.owner.from_arcis(book)
+        (book_version.reveal(), receiver.from_arcis(result))
+    }
+
+    /// Read-only depth view: resting size in four bands of `band_width` below
+    /// and above mid. Mid is rounded down to a band boundary and every band
+    /// total down to a multiple of `lot_size`. A band holding fewer than
+    /// `min_orders` orders reports zero, so no single order shows on its own.
+    #[instruction]
+    pub fn reveal_depth(
+        book_ctxt: Enc<Mxe, OrderBook>,
+        band_width: u64,
+        lot_size: u64,
+        min_orders: u64,
+        book_version: u64,
+    ) -> (u64, DepthSnapshot) {
+        let book = book_ctxt.to_arcis();
+
+        let mut best_bid = 0u64;
+        let mut has_bid = false;
+        let mut best_ask = 0u64;
+        let mut has_ask = false;
+        for i in 0..10 {
+            let buy = book.buy_orders[i as usize];
+            if i < book.buy_count && (!has_bid || buy.price > best_bid) {
+                best_bid = buy.price;
+                has_bid = true;
+            }
+            let sell = book.sell_orders[i as usize];
+            if i < book.sell_count && (!has_ask || sell.price < best_ask) {
+                best_ask = sell.price;
+                has_ask = true;
+            }
+        }
+        let mid = if has_bid && has_ask {
+            ((best_bid as u128 + best_ask as u128) / 2) as u64
+        } else if has_bid {
+            best_bid
+        } else {
+            best_ask
+        };
+
+        let mut bid_depth = [0u64; 4];
+        let mut ask_depth = [0u64; 4];
+        let mut bid_orders = [0u64; 4];
+        let mut ask_orders = [0u64; 4];
+        for i in 0..10 {
+            let buy = book.buy_orders[i as usize];
+            let bid_distance = if buy.price <= mid { mid - buy.price } else { 0 };
+            let sell = book.sell_orders[i as usize];
+            let ask_distance = if sell.price >= mid { sell.price - mid } else { 0 };
+            for band in 0..4 {
+                let lower = band_width * band as u64;
+                let upper = lower + band_width;
+                let in_bid_band = i < book.buy_count
+                    && buy.price <= mid
+                    && bid_distance >= lower
+                    && bid_distance < upper;
+                if in_bid_band {
+                    bid_depth[band] += buy.quantity;
+                    bid_orders[band] += 1;
+                }
+                let in_ask_band = i < book.sell_count
+                    && sell.price >= mid
+                    && ask_distance >= lower
+                    && ask_distance < upper;
+                if in_ask_band {
+                    ask_depth[band] += sell.quantity;
+                    ask_orders[band] += 1;
+                }
+            }
+        }
+        for band in 0..4 {
+            bid_depth[band] = if bid_orders[band] >= min_orders {
+                bid_depth[band] / lot_size * lot_size
+            } else {
+                0
+            };
+            ask_depth[band] = if ask_orders[band] >= min_orders {
+                ask_depth[band] / lot_size * lot_size
+            } else {
+                0
+            };
+        }
+
+        let snapshot = DepthSnapshot {
+            mid_price: mid / band_width * band_width,
+            bid_depth,
+            ask_depth,
+        };
+
+        (book_version.reveal(), snapshot.reveal())
+    }
+
+    /// Buy size willing to pay at least `price` and sell size willing to take
+    /// at most `price`, among orders stamped before `order_cutoff`
+    fn size_at(book: &OrderBook, price: u64, order_cutoff: u64) -> (u64, u64) {
+        let mut demand = 0u64;
+        let mut supply = 0u64;
+        for i in 0..10 {
+            let buy = book.buy_orders[i as usize];
+            if i < book.buy_count && buy.slot < order_cutoff && buy.price >= price {
+                demand += buy.quantity;
+            }
+            let sell = book.sell_orders[i as usize];
+            if i < book.sell_count && sell.slot < order_cutoff && sell.price <= price {
+                supply += sell.quantity;
+            }
+        }
+        (demand, supply)
+    }
+
+    /// Uniform clearing price of the orders stamped before `order_cutoff`:
+    /// among their prices, the one that would execute the most size, breaking
+    /// ties by the smaller imbalance. Returns the price with the demand and
+    /// supply at it; the book crosses when both are non-zero.
+    fn uncross(book: &OrderBook, order_cutoff: u64) -> (u64, u64, u64) {
+        let mut best_price = 0u64;
+        let mut best_executable = 0u64;
+        let mut best_imbalance = 0u64;
+        let mut best_demand = 0u64;
+        let mut best_supply = 0u64;
+        for i in 0..20 {
+            let is_buy = i < 10;
+            let idx = if is_buy { i } else { i - 10 };
+            let candidate = if is_buy {
+                book.buy_orders[idx as usize]
+            } else {
+                book.sell_orders[idx as usize]
+            };
+            let exists = if is_buy {
+                idx < book.buy_count
+            } else {
+                idx < book.sell_count
+            };
+
+            let (demand, supply) = size_at(book, candidate.price, order_cutoff);
+            let executable = if demand < supply { demand } else { supply };
+            let imbalance = if demand > supply {
+                demand - supply
+            } else {
+                supply - demand
+            };
+            let better = executable > best_executable
+                || (executable == best_executable
+                    && executable > 0
+                    && imbalance < best_imbalance);
+            if exists && candidate.slot < order_cutoff && better {
+                best_price = candidate.price;
+                best_executable = executable;
+                best_imbalance = imbalance;
+                best_demand = demand;
+                best_supply = supply;
+            }
+        }
+        if best_executable > 0 {
+            (best_price, best_demand, best_supply)
+        } else {
+            (0, 0, 0)
+        }
+    }
+
+    /// Size each order on one side of the book fills when the batch uncrosses
+    /// `volume` at `price`. Orders priced better than the clearing price fill
+    /// in full and the orders at it share the rest pro rata to their size, so
+    /// arrival time does not decide who fills within a batch. Shares are
+    /// rounded down to whole lots and the lots that leaves over go one each
+    /// to the orders with the largest remainders.
+    fn allocate(
+        orders: &[Order; 10],
+        count: u8,
+        is_buy: bool,
+        price: u64,
+        volume: u64,
+        order_cutoff: u64,
+        lot_size: u64,
+    ) -> [u64; 10] {
+        let mut shares = [0u64; 10];
+        let mut better = 0u64;
+        let mut at_price = 0u64;
+        for i in 0..10 {
+            let order = orders[i as usize];
+            let eligible = i < count && order.slot < order_cutoff;
+            let improves = if is_buy {
+                order.price > price
+            } else {
+                order.price < price
+            };
+            if eligible && improves {
+                shares[i as usize] = order.quantity;
+                better += order.quantity;
+            }
+            if eligible && order.price == price {
+                at_price += order.quantity;
+            }
+        }
+
+        let left = if volume > better { volume - better } else { 0 };
+        let rest = if left < at_price { left } else { at_price };
+        let mut remainders = [0u128; 10];
+        let mut allocated = 0u64;
+        for i in 0..10 {
+            let order = orders[i as usize];
+            let at = i < count && order.slot < order_cutoff && order.price == price;
+            if at {
+                let scaled = order.quantity as u128 * rest as u128;
+                let lots = scaled / (at_price as u128 * lot_size as u128);
+                let share = (lots * lot_size as u128) as u64;
+                shares[i as usize] = share;
+                allocated += share;
+                remainders[i as usize] = scaled - share as u128 * at_price as u128;
+            }
+        }
+
+        // Fewer lots are left over than there are orders at the price, and
+        // only an order short of its full size has a remainder
+        let mut leftover = rest - allocated;
+        let mut topped_up = [false; 10];
+        for _ in 0..10 {
+            let mut best = 10u8;
+            let mut best_remainder = 0u128;
+            for i in 0..10 {
+                if !topped_up[i as usize] && remainders[i as usize] > best_remainder {
+                    best = i;
+                    best_remainder = remainders[i as usize];
+                }
+            }
+            let top_up = leftover >= lot_size && best < 10;
+            for i in 0..10 {
+                if top_up && i == best {
+                    shares[i as usize] += lot_size;
+                    topped_up[i as usize] = true;
+                }
+            }
+            if top_up {
+                leftover -= lot_size;
+            }
+        }
+        shares
+    }
+
+    /// Read-only uncross: the uniform price match_orders would execute the
+    /// orders stamped before `order_cutoff` at. Only the price and which side
+    /// would be left over are revealed.
+    #[instruction]
+    pub fn indicative_price(
+        book_ctxt: Enc<Mxe, OrderBook>,
+        order_cutoff: u64,
+        book_version: u64,
+    ) -> (u64, IndicativePrice) {
+        let book = book_ctxt.to_arcis();
+        let (price, demand, supply) = uncross(&book, order_cutoff);
+
+        let crossed = demand > 0 && supply > 0;
+        let imbalance_side = if !crossed || demand == supply {
+            0u8
+        } else if demand > supply {
+            1u8
+        } else {
+            2u8
+        };
+        let indicative = IndicativePrice {
+            price,
+            crossed,
+            imbalance_side,
+        };
+
+        (book_version.reveal(), indicative.reveal())
     }
 
     /// Helper function to remove filled orders and compact the order arrays
@@ -213,93 +892,465 @@ mod circuits {
         book.sell_count = write_idx;
     }
 
+    /// Keep only the trades a trader took part in; other slots stay zeroed
+    fn fill_report(trades: &[Trade; 5], trade_count: u8, trader_id: u128) -> FillReport {
+        let mut report = FillReport::new();
+        for i in 0..5 {
+            let trade = trades[i as usize];
+            let involved = i < trade_count
+                && (trade.buyer_id == trader_id || trade.seller_id == trader_id);
+            if involved {
+                report.trades[i as usize] = trade;
+                report.trade_count += 1;
+            }
+        }
+        report
+    }
+
+    /// Discount of the highest tier `volume` reaches, in basis points of the
+    /// fee; tier thresholds are ascending
+    fn tier_discount_bps(volume: u64, tier_volumes: &[u64; 3], tier_discounts: &[u64; 3]) -> u64 {
+        let mut discount = 0u64;
+        for i in 0..3 {
+            if volume >= tier_volumes[i] {
+                discount = tier_discounts[i];
+            }
+        }
+        discount
+    }
+
+    /// Quote atoms for `quantity` base atoms at `price`, which is quoted per
+    /// `price_scale` base atoms. Buyers pay the amount rounded up and sellers
+    /// receive it rounded down, so the vault keeps any remainder as dust.
+    fn notional(price: u64, quantity: u64, price_scale: u64, round_up: bool) -> u128 {
+        let product = price as u128 * quantity as u128;
+        let scale = price_scale as u128;
+        let floor = product / scale;
+        if round_up && floor * scale < product {
+            floor + 1
+        } else {
+            floor
+        }
+    }
+
+    /// Fee of `fee_bps` basis points on `amount`, rounded up
+    fn fee_on(amount: u128, fee_bps: u64) -> u128 {
+        (amount * fee_bps as u128 + 9_999) / 10_000
+    }
+
+    /// Tier discount of `discount_bps` on a fee; the discount never cuts
+    /// into the part of the fee paid out as rebate and referral
+    fn fee_discount(fee: u64, payouts: u64, discount_bps: u64) -> u64 {
+        let discount = (fee as u128 * discount_bps as u128 / 10_000) as u64;
+        let kept = fee - payouts;
+        if discount < kept {
+            discount
+        } else {
+            kept
+        }
+    }
+
+    /// `a + b`, capped at u64::MAX
+    fn saturating_add(a: u64, b: u64) -> u64 {
+        if a > u64::MAX - b {
+            u64::MAX
+        } else {
+            a + b
+        }
+    }
+
+    /// Traders of the first `trade_count` trades sorted by ID, with repeats
+    /// and unused slots zeroed so the list does not pair buyers with sellers
+    fn participants(trades: &[Trade; 5], trade_count: u8) -> [u128; 10] {
+        let mut ids = [0u128; 10];
+        for i in 0..5 {
+            if i < trade_count {
+                ids[2 * i as usize] = trades[i as usize].buyer_id;
+                ids[2 * i as usize + 1] = trades[i as usize].seller_id;
+            }
+        }
+        for _ in 0..10 {
+            for j in 0..9 {
+                let low = ids[j];
+                let high = ids[j + 1];
+                if low > high {
+                    ids[j] = high;
+                    ids[j + 1] = low;
+                }
+            }
+        }
+        let mut unique = ids;
+        for j in 1..10 {
+            if ids[j] == ids[j - 1] {
+                unique[j] = 0;
+            }
+        }
+        unique
+    }
+
+    /// Matches the whole book at the uniform clearing price indicative_price
+    /// publishes. Orders priced through it fill in full and the orders at it
+    /// share what is left pro rata, so within a batch only price decides who
+    /// fills. The fills are encrypted to the MXE and only the sorted list of
+    /// traders in them is revealed, so each can settle their share with
+    /// `settle_fills`.
+    ///
+    /// When `disclose` is set the pair settles inside its vaults: fills must be
+    /// covered by order escrow and trade prices and sizes are revealed.
+    /// Of the two orders in a fill the later stamped one is the taker, and
+    /// orders sharing a stamp both pay the maker rate; fees are charged
+    /// in quote at the pair's base rates on top of the buyer's payment and out
+    /// of the seller's proceeds, and the maker's rebate and the taker's
+    /// referrer share both come out of the taker's fee.
+    /// The pair's auditor receives every trade when `audit` is set, and the
+    /// prints are encrypted to the MXE for the delayed tape.
+    /// Orders stamped at or after `order_cutoff` belong to an auction epoch
+    /// still open and wait for the next match.
     #[instruction]
     pub fn match_orders(
         book_ctxt: Enc<Mxe, OrderBook>,
         timestamp: u64,
-    ) -> Enc<Mxe, MatchResult> {
+        order_cutoff: u64,
+        book_version: u64,
+        disclose: bool,
+        price_scale: u64,
+        lot_size: u64,
+        maker_fee_bps: u64,
+        taker_fee_bps: u64,
+        maker_rebate_bps: u64,
+        referral_share_bps: u64,
+        audit: bool,
+        auditor: Shared,
+        fills_owner: Mxe,
+        tape: Mxe,
+    ) -> (
+        Enc<Mxe, OrderBook>,
+        u64,
+        MatchSummary,
+        TradeDisclosure,
+        Enc<Mxe, MatchFills>,
+        Enc<Shared, FillReport>,
+        Enc<Mxe, TapeBatch>,
+    ) {
         let mut book = book_ctxt.to_arcis();
-        let mut trades = [Trade::new(); 5];
+        let mut fills = MatchFills::new();
         let mut trade_count = 0u8;
 
+        // Every fill executes at the batch's uniform clearing price, the one
+        // indicative_price publishes, and each order fills its share of the
+        // executable size
+        let (clearing_price, demand, supply) = uncross(&book, order_cutoff);
+        let volume = if demand < supply { demand } else { supply };
+        let mut buy_left = allocate(
+            &book.buy_orders,
+            book.buy_count,
+            true,
+            clearing_price,
+            volume,
+            order_cutoff,
+            lot_size,
+        );
+        let mut sell_left = allocate(
+            &book.sell_orders,
+            book.sell_count,
+            false,
+            clearing_price,
+            volume,
+            order_cutoff,
+            lot_size,
+        );
+
         // Track which orders have been fully filled
         let mut buy_filled = [false; 10];
         let mut sell_filled = [false; 10];
 
-        // Iterate through buy orders - match each buy against all sells
+        // Pair the buy shares with the sell shares in book order
         for buy_idx in 0..10 {
-            let should_process_buy = buy_idx < book.buy_count && trade_count < 5;
-
-            if should_process_buy {
-                let mut buy_order = book.buy_orders[buy_idx as usize];
-                let buy_is_active = !buy_filled[buy_idx as usize] && buy_order.quantity > 0;
-
-                if buy_is_active {
-                    // Find matching sell orders
-                    for sell_idx in 0..10 {
-                        let should_process_sell = sell_idx < book.sell_count &&
-                                                 trade_count < 5 &&
-                                                 buy_order.quantity > 0;
-
-                        if should_process_sell {
-                            let mut sell_order = book.sell_orders[sell_idx as usize];
-                            let sell_is_active = !sell_filled[sell_idx as usize] && sell_order.quantity > 0;
-
-                            // Price match condition: buy price >= sell price
-                            let prices_match = buy_order.price >= sell_order.price;
-
-                            if sell_is_active && prices_match {
-                                // Determine trade quantity (minimum of buy and sell quantities)
-                                let trade_quantity = if buy_order.quantity < sell_order.quantity {
-                                    buy_order.quantity
-                                } else {
-                                    sell_order.quantity
-                                };
-
-                                // Use sell price (provides price improvement for buyer)
-                                let trade_price = sell_order.price;
-
-                                // Record the trade
-                                trades[trade_count as usize] = Trade {
-                                    buyer_id: buy_order.trader_id,
-                                    seller_id: sell_order.trader_id,
-                                    price: trade_price,
-                                    quantity: trade_quantity,
-                                    timestamp,
-                                };
-                                trade_count += 1;
-
-                                // Update order quantities after match
-                                buy_order.quantity -= trade_quantity;
-                                sell_order.quantity -= trade_quantity;
-
-                                // Mark orders as filled if quantity reaches zero
-                                if buy_order.quantity == 0 {
-                                    buy_filled[buy_idx as usize] = true;
-                                }
-                                if sell_order.quantity == 0 {
-                                    sell_filled[sell_idx as usize] = true;
-                                }
-
-                                // Update orders in the book
-                                book.buy_orders[buy_idx as usize] = buy_order;
-                                book.sell_orders[sell_idx as usize] = sell_order;
-                            }
-                        }
+            let mut buy_order = book.buy_orders[buy_idx as usize];
+            for sell_idx in 0..10 {
+                let mut sell_order = book.sell_orders[sell_idx as usize];
+                let trade_quantity = if buy_left[buy_idx as usize] < sell_left[sell_idx as usize] {
+                    buy_left[buy_idx as usize]
+                } else {
+                    sell_left[sell_idx as usize]
+                };
+                let trade_price = clearing_price;
+
+                // The order stamped later crossed the spread. Orders sharing
+                // a stamp arrived together, in the same auction epoch, so
+                // neither is the taker: both pay the maker rate and no rebate
+                // or referral share comes out of their fees.
+                let simultaneous = buy_order.slot == sell_order.slot;
+                let buyer_is_taker = buy_order.slot > sell_order.slot;
+                let (buyer_fee_bps, seller_fee_bps) = if simultaneous {
+                    (maker_fee_bps, maker_fee_bps)
+                } else if buyer_is_taker {
+                    (taker_fee_bps, maker_fee_bps)
+                } else {
+                    (maker_fee_bps, taker_fee_bps)
+                };
+                let cost = notional(trade_price, trade_quantity, price_scale, true);
+                let proceeds = notional(trade_price, trade_quantity, price_scale, false);
+                let buyer_fee = fee_on(cost, buyer_fee_bps);
+                let seller_fee = fee_on(proceeds, seller_fee_bps);
+
+                // Both rounded down, and the rebate is capped by what the
+                // referral leaves of the taker's fee, so the fee vault never
+                // pays out. Only a taker order placed with a referrer carries
+                // a referral share.
+                let (taker_fee, taker_referred) = if simultaneous {
+                    (0, false)
+                } else if buyer_is_taker {
+                    (buyer_fee, buy_order.has_referrer)
+                } else {
+                    (seller_fee, sell_order.has_referrer)
+                };
+                let referral = if taker_referred {
+                    taker_fee * referral_share_bps as u128 / 10_000
+                } else {
+                    0
+                };
+                let full_rebate = proceeds * maker_rebate_bps as u128 / 10_000;
+                let rebate = if full_rebate < taker_fee - referral {
+                    full_rebate
+                } else {
+                    taker_fee - referral
+                };
+
+                // Settled fills and the buyer's fee must be paid for out of
+                // the orders' escrow
+                let funded = !disclose
+                    || (buy_order.escrow as u128 >= cost + buyer_fee
+                        && sell_order.escrow >= trade_quantity);
+
+                if trade_quantity > 0 && trade_count < 5 && funded {
+                    let slot = trade_count as usize;
+
+                    // Record the trade
+                    fills.trades[slot] = Trade {
+                        buyer_id: buy_order.trader_id,
+                        seller_id: sell_order.trader_id,
+                        price: trade_price,
+                        quantity: trade_quantity,
+                        timestamp,
+                    };
+                    fills.buyer_fees[slot] = buyer_fee as u64;
+                    fills.seller_fees[slot] = seller_fee as u64;
+                    fills.rebates[slot] = rebate as u64;
+                    fills.referrals[slot] = referral as u64;
+                    fills.buyer_is_taker[slot] = buyer_is_taker;
+                    trade_count += 1;
+
+                    // Update order quantities after match
+                    buy_left[buy_idx as usize] -= trade_quantity;
+                    sell_left[sell_idx as usize] -= trade_quantity;
+                    buy_order.quantity -= trade_quantity;
+                    sell_order.quantity -= trade_quantity;
+                    if disclose {
+                        buy_order.escrow -= (cost + buyer_fee) as u64;
+                        sell_order.escrow -= trade_quantity;
                     }
+
+                    // Orders that reach zero quantity leave the book with
+                    // whatever escrow they have left
+                    if buy_order.quantity == 0 {
+                        buy_filled[buy_idx as usize] = true;
+                        fills.buyer_closed[slot] = true;
+                        fills.buyer_releases[slot] = buy_order.escrow;
+                    }
+                    if sell_order.quantity == 0 {
+                        sell_filled[sell_idx as usize] = true;
+                        fills.seller_closed[slot] = true;
+                        fills.seller_releases[slot] = sell_order.escrow;
+                    }
+
+                    // Update orders in the book
+                    book.buy_orders[buy_idx as usize] = buy_order;
+                    book.sell_orders[sell_idx as usize] = sell_order;
                 }
             }
         }
+        fills.trade_count = trade_count;
+
+        let mut disclosure = TradeDisclosure::new();
+        if disclose {
+            for i in 0..5 {
+                disclosure.prices[i] = fills.trades[i].price;
+                disclosure.quantities[i] = fills.trades[i].quantity;
+            }
+            disclosure.trade_count = trade_count;
+        }
+        let summary = MatchSummary {
+            trade_count,
+            participants: participants(&fills.trades, trade_count),
+        };
 
         // Remove filled orders from the book and compact arrays
         compact_orders(&mut book, &buy_filled, &sell_filled);
 
-        let result = MatchResult {
-            trades,
+        let mut audit_record = FillReport::new();
+        if audit {
+            audit_record.trades = fills.trades;
+            audit_record.trade_count = trade_count;
+        }
+
+        let mut batch = TapeBatch {
+            prices: [0; 5],
+            quantities: [0; 5],
+            timestamps: [0; 5],
             trade_count,
-            order_book: book,
         };
+        for i in 0..5 {
+            batch.prices[i] = fills.trades[i].price;
+            batch.quantities[i] = fills.trades[i].quantity;
+            batch.timestamps[i] = fills.trades[i].timestamp;
+        }
+
+        (
+            book_ctxt.owner.from_arcis(book),
+            book_version.reveal(),
+            summary.reveal(),
+            disclosure.reveal(),
+            fills_owner.from_arcis(fills),
+            auditor.from_arcis(audit_record),
+            tape.from_arcis(batch),
+        )
+    }
 
-        book_ctxt.owner.from_arcis(result)
+    /// One trader's share of a recorded match: their fills encrypted to their
+    /// registered key and the escrow their closed orders release. When
+    /// `disclose` is set their net vault movement is revealed as well, with
+    /// fees at the pair's base rates so they do not give the trader's tier
+    /// away. The trader's cumulative quote volume stays MXE-encrypted and
+    /// selects their fee tier, whose discount accrues to an MXE-encrypted
+    /// credit the trader claims with claim_fee_credit.
+    #[instruction]
+    pub fn settle_fills(
+        fills_ctxt: Enc<Mxe, MatchFills>,
+        trader_id: u128,
+        disclose: bool,
+        price_scale: u64,
+        tier_volume_0: u64,
+        tier_discount_0: u64,
+        tier_volume_1: u64,
+        tier_discount_1: u64,
+        tier_volume_2: u64,
+        tier_discount_2: u64,
+        has_volume: bool,
+        volume_ctxt: Enc<Mxe, TraderVolume>,
+        receiver: Shared,
+    ) -> (Enc<Shared, FillReport>, Settlement, Enc<Mxe, TraderVolume>) {
+        let fills = fills_ctxt.to_arcis();
+        // A trader without a stored volume has not traded yet
+        let mut state = if has_volume {
+            volume_ctxt.to_arcis()
+        } else {
+            TraderVolume {
+                volume: 0,
+                fee_credit: 0,
+            }
+        };
+        let discount_bps = tier_discount_bps(
+            state.volume,
+            &[tier_volume_0, tier_volume_1, tier_volume_2],
+            &[tier_discount_0, tier_discount_1, tier_discount_2],
+        );
+        // Tier discounts are non-decreasing, so the last is the highest
+        let max_discount_bps = tier_discount_2;
+
+        let mut settlement = Settlement::new();
+        let mut traded = 0u64;
+        let mut credit = 0u64;
+        for i in 0..5 {
+            let idx = i as usize;
+            let trade = fills.trades[idx];
+            let counted = i < fills.trade_count;
+            let cost = notional(trade.price, trade.quantity, price_scale, true) as u64;
+            let proceeds = notional(trade.price, trade.quantity, price_scale, false) as u64;
+            // What the taker's fee pays out to the maker and the referrer
+            let payouts = fills.rebates[idx] + fills.referrals[idx];
+            if counted && trade.buyer_id == trader_id {
+                let buyer_is_taker = fills.buyer_is_taker[idx];
+                settlement.base_credit += trade.quantity;
+                settlement.quote_debit += cost;
+                let buy_payouts = if buyer_is_taker { payouts } else { 0 };
+                settlement.buy_fee += fills.buyer_fees[idx];
+                settlement.credit_reserve +=
+                    fee_discount(fills.buyer_fees[idx], buy_payouts, max_discount_bps);
+                credit += fee_discount(fills.buyer_fees[idx], buy_payouts, discount_bps);
+                if buyer_is_taker {
+                    settlement.referral += fills.referrals[idx];
+                } else {
+                    settlement.rebate += fills.rebates[idx];
+                }
+                if fills.buyer_closed[idx] {
+                    settlement.quote_release += fills.buyer_releases[idx];
+                    settlement.orders_closed += 1;
+                }
+                settlement.fill_count += 1;
+                traded += proceeds;
+            }
+            if counted && trade.seller_id == trader_id {
+                let seller_is_taker = !fills.buyer_is_taker[idx];
+                settlement.quote_credit += proceeds;
+                settlement.base_debit += trade.quantity;
+                let sell_payouts = if seller_is_taker { payouts } else { 0 };
+                settlement.sell_fee += fills.seller_fees[idx];
+                settlement.credit_reserve +=
+                    fee_discount(fills.seller_fees[idx], sell_payouts, max_discount_bps);
+                credit += fee_discount(fills.seller_fees[idx], sell_payouts, discount_bps);
+                if seller_is_taker {
+                    settlement.referral += fills.referrals[idx];
+                } else {
+                    settlement.rebate += fills.rebates[idx];
+                }
+                if fills.seller_closed[idx] {
+                    settlement.base_release += fills.seller_releases[idx];
+                    settlement.orders_closed += 1;
+                }
+                settlement.fill_count += 1;
+                traded += proceeds;
+            }
+        }
+        // Private fills settle outside the vaults and pay no fee, but closed
+        // orders still hand back their escrow and leave the trader's open
+        // order count
+        if disclose {
+            state.fee_credit = saturating_add(state.fee_credit, credit);
+        } else {
+            let mut released = Settlement::new();
+            released.base_release = settlement.base_release;
+            released.quote_release = settlement.quote_release;
+            released.orders_closed = settlement.orders_closed;
+            settlement = released;
+        }
+        state.volume = saturating_add(state.volume, traded);
+
+        let report = fill_report(&fills.trades, fills.trade_count, trader_id);
+
+        (
+            receiver.from_arcis(report),
+            settlement.reveal(),
+            volume_ctxt.owner.from_arcis(state),
+        )
+    }
+
+    /// Reveal a trader's unclaimed fee credit and zero it, keeping their
+    /// volume. Claims are made when the trader chooses, so the amount sums
+    /// discounts over any number of matches rather than pricing one fill.
+    #[instruction]
+    pub fn claim_fee_credit(volume_ctxt: Enc<Mxe, TraderVolume>) -> (u64, Enc<Mxe, TraderVolume>) {
+        let mut state = volume_ctxt.to_arcis();
+        let credit = state.fee_credit;
+        state.fee_credit = 0;
+        (credit.reveal(), volume_ctxt.owner.from_arcis(state))
+    }
+
+    /// Decrypt a tape entry whose delay has passed; the sequence is echoed so
+    /// the program can check the entry was not replaced in the meantime
+    #[instruction]
+    pub fn reveal_tape(batch_ctxt: Enc<Mxe, TapeBatch>, sequence: u64) -> (u64, TapeBatch) {
+        let batch = batch_ctxt.to_arcis();
+        (sequence.reveal(), batch.reveal())
     }
 }
\ No newline at end of file
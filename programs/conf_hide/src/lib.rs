@@ -1,12 +1,87 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        default_account_state::DefaultAccountState, mint_close_authority::MintCloseAuthority,
+        transfer_fee::TransferFeeConfig, transfer_hook::TransferHook, BaseStateWithExtensions,
+        ExtensionType, StateWithExtensions,
+    },
+    state::AccountState,
+};
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface, TransferChecked,
+};
 use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::{CallbackAccount, X25519Pubkey};
 
 // Computation definition offsets for our MPC instructions
 const COMP_DEF_OFFSET_INIT_ORDER_BOOK: u32 = comp_def_offset("init_order_book");
 const COMP_DEF_OFFSET_SUBMIT_ORDER: u32 = comp_def_offset("submit_order");
 const COMP_DEF_OFFSET_CANCEL_ORDER: u32 = comp_def_offset("cancel_order");
 const COMP_DEF_OFFSET_MATCH_ORDERS: u32 = comp_def_offset("match_orders");
+const COMP_DEF_OFFSET_QUERY_MY_ORDERS: u32 = comp_def_offset("query_my_orders");
+const COMP_DEF_OFFSET_REVEAL_DEPTH: u32 = comp_def_offset("reveal_depth");
+const COMP_DEF_OFFSET_INDICATIVE_PRICE: u32 = comp_def_offset("indicative_price");
+const COMP_DEF_OFFSET_REVEAL_TAPE: u32 = comp_def_offset("reveal_tape");
+const COMP_DEF_OFFSET_SETTLE_FILLS: u32 = comp_def_offset("settle_fills");
+const COMP_DEF_OFFSET_CLAIM_FEE_CREDIT: u32 = comp_def_offset("claim_fee_credit");
+
+/// Distinct traders the five fills of a match can involve
+const MATCH_PARTICIPANTS: usize = 10;
+
+/// Ciphertexts of the MXE-encrypted fills a match records
+const MATCH_FILLS_CIPHERTEXTS: usize = 71;
+
+/// Ciphertexts of the MXE-encrypted order book: nine fields for each of 10
+/// buys, the buy count, the same for 10 sells, the sell count and the next
+/// order ID
+const ORDER_BOOK_CIPHERTEXTS: usize = 183;
+
+/// Pages of five orders query_my_orders can return from a 20-order book
+const MAX_ORDER_PAGES: u8 = 4;
+
+/// Price bands reveal_depth reports on each side of mid
+const DEPTH_BANDS: u64 = 4;
+
+/// Match batches the trade tape holds; the oldest is overwritten, revealed or not
+const TAPE_CAPACITY: usize = 8;
+
+/// Seconds a tape entry stays encrypted unless the authority configures otherwise
+const DEFAULT_TAPE_DELAY_SECONDS: i64 = 15 * 60;
+
+/// Slots between indicative price reveals unless the authority configures otherwise
+const DEFAULT_INDICATIVE_INTERVAL_SLOTS: u64 = 150;
+
+/// Highest maker or taker fee a pair may charge, in basis points
+const MAX_FEE_BPS: u16 = 1_000;
+
+/// Largest reward a pair may pay per productive match, in whole quote tokens
+const MAX_CRANK_REWARD_TOKENS: u64 = 10;
+
+/// Largest bond a pair may ask of a cranker per match, in lamports
+const MAX_MATCH_BOND: u64 = 1_000_000_000;
+
+/// Largest order notional, in quote atoms, that still fits in a u64 with the
+/// highest fee added on top
+const MAX_ORDER_NOTIONAL: u64 = (u64::MAX as u128 / (10_000 + MAX_FEE_BPS as u128) * 10_000) as u64;
+
+/// Volume tiers above the base fee rate the settle_fills circuit selects from
+const FEE_TIERS: usize = 3;
+
+/// Token-2022 mint extensions a pair cannot hold safely in its vaults: tokens
+/// that cannot move, balances that move confidentially where the vault cannot
+/// see them, and a delegate that could drain the vaults
+const UNSUPPORTED_MINT_EXTENSIONS: [ExtensionType; 4] = [
+    ExtensionType::NonTransferable,
+    ExtensionType::ConfidentialTransferMint,
+    ExtensionType::ConfidentialTransferFeeConfig,
+    ExtensionType::PermanentDelegate,
+];
+
+/// Slots after which a queued computation without a callback may be cleared or re-queued
+const COMPUTATION_TIMEOUT_SLOTS: u64 = 1_500;
 
 declare_id!("FtJt3sRDe5cGjRMFQg1Z2ngcsTfccYe3E8CtnFaC6P8e");
 
@@ -38,32 +113,152 @@ pub mod conf_hide {
         Ok(())
     }
 
+    /// Initialize computation definition for querying a trader's own orders
+    pub fn init_query_my_orders_comp_def(ctx: Context<InitQueryMyOrdersCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Initialize computation definition for bucketed depth disclosure
+    pub fn init_reveal_depth_comp_def(ctx: Context<InitRevealDepthCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Initialize computation definition for indicative clearing prices
+    pub fn init_indicative_price_comp_def(
+        ctx: Context<InitIndicativePriceCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Initialize computation definition for revealing delayed trade prints
+    pub fn init_reveal_tape_comp_def(ctx: Context<InitRevealTapeCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Initialize computation definition for settling a trader's fills
+    pub fn init_settle_fills_comp_def(ctx: Context<InitSettleFillsCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Initialize computation definition for claiming a trader's fee credit
+    pub fn init_claim_fee_credit_comp_def(ctx: Context<InitClaimFeeCreditCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
     /// Initialize a new trading pair with empty order book
     pub fn initialize_trading_pair(
         ctx: Context<InitializeTradingPair>,
         computation_offset: u64,
         trading_pair_id: u64,
         mxe_nonce: u128,
+        disclosure_policy: DisclosurePolicy,
+        market: MarketParams,
     ) -> Result<()> {
-        // Validate that the provided accounts are actually valid mints
-        // This is done by attempting to deserialize them
-        let base_mint_data = ctx.accounts.base_mint.try_borrow_data()?;
-        let quote_mint_data = ctx.accounts.quote_mint.try_borrow_data()?;
-        require!(base_mint_data.len() >= 82, ErrorCode::InvalidTokenAccount); // Mint account size
-        require!(quote_mint_data.len() >= 82, ErrorCode::InvalidTokenAccount);
+        // Both mints are owned by SPL Token or Token-2022 and differ, checked
+        // by the account constraints
+        validate_mint_extensions(&ctx.accounts.base_mint.to_account_info())?;
+        validate_mint_extensions(&ctx.accounts.quote_mint.to_account_info())?;
+        let base_decimals = ctx.accounts.base_mint.decimals;
+        let quote_decimals = ctx.accounts.quote_mint.decimals;
+        market.validate(base_decimals, quote_decimals)?;
 
         let trading_pair = &mut ctx.accounts.trading_pair;
         trading_pair.bump = ctx.bumps.trading_pair;
         trading_pair.trading_pair_id = trading_pair_id;
         trading_pair.base_mint = ctx.accounts.base_mint.key();
         trading_pair.quote_mint = ctx.accounts.quote_mint.key();
-        trading_pair.order_book = [0; 32]; // Will be populated by MPC callback
         trading_pair.order_book_nonce = 0;
         trading_pair.is_active = true;
         trading_pair.total_orders = 0;
+        trading_pair.book_version = 0;
+        trading_pair.computation_count = 0;
+        // Hold the lock until the empty book lands so nothing is queued against it
+        trading_pair.book_locked = true;
+        trading_pair.base_vault = Pubkey::default();
+        trading_pair.quote_vault = Pubkey::default();
+        trading_pair.disclosure_policy = disclosure_policy;
+        trading_pair.authority = ctx.accounts.payer.key();
+        trading_pair.depth_enabled = false;
+        trading_pair.depth_band_width = 0;
+        trading_pair.depth_lot_size = 0;
+        trading_pair.depth_min_orders = 0;
+        trading_pair.depth_interval_slots = 0;
+        trading_pair.last_depth_slot = 0;
+        trading_pair.auditor_pubkey = [0; 32];
+        trading_pair.auditor_nonce = 0;
+        trading_pair.tick_size = market.tick_size;
+        trading_pair.lot_size = market.lot_size;
+        trading_pair.min_order_size = market.min_order_size;
+        trading_pair.max_order_price = market.max_order_price;
+        trading_pair.max_order_quantity = market.max_order_quantity;
+        trading_pair.price_scale = market.price_scale;
+        trading_pair.base_decimals = base_decimals;
+        trading_pair.quote_decimals = quote_decimals;
+        trading_pair.quote_dust = 0;
+        trading_pair.base_token_program = *ctx.accounts.base_mint.to_account_info().owner;
+        trading_pair.quote_token_program = *ctx.accounts.quote_mint.to_account_info().owner;
+        trading_pair.maker_fee_bps = 0;
+        trading_pair.taker_fee_bps = 0;
+        trading_pair.maker_rebate_bps = 0;
+        trading_pair.referral_share_bps = 0;
+        trading_pair.referral_owed = 0;
+        trading_pair.match_interval_slots = 0;
+        trading_pair.last_empty_match_slot = 0;
+        trading_pair.empty_match_version = u64::MAX;
+        trading_pair.crank_reward = 0;
+        trading_pair.match_bond = 0;
+        trading_pair.auction_interval_slots = 0;
+        trading_pair.last_auction_epoch = 0;
+        trading_pair.session_state = SessionState::Continuous;
+        trading_pair.session_started_slot = Clock::get()?.slot;
+        trading_pair.session_schedule = SessionSchedule::default();
+        trading_pair.uncrossed_session_slot = 0;
+        trading_pair.fee_vault = Pubkey::default();
+        trading_pair.fees_collected = 0;
+        trading_pair.fee_tiers = [FeeTier::DISABLED; FEE_TIERS];
+        trading_pair.match_count = 0;
+        trading_pair.indicative_interval_slots = DEFAULT_INDICATIVE_INTERVAL_SLOTS;
+        trading_pair.last_indicative_slot = 0;
+        trading_pair.fee_credit_reserved = 0;
+
+        let trading_pair_key = trading_pair.key();
+        trading_pair.claim_computation_offset(&trading_pair_key, computation_offset)?;
+
+        let trade_tape = &mut ctx.accounts.trade_tape;
+        trade_tape.trading_pair = trading_pair_key;
+        trade_tape.delay_seconds = DEFAULT_TAPE_DELAY_SECONDS;
+        trade_tape.next_sequence = 0;
+        trade_tape.bump = ctx.bumps.trade_tape;
+
+        let order_book = &mut ctx.accounts.order_book;
+        order_book.ciphertexts = [[0; 32]; ORDER_BOOK_CIPHERTEXTS];
+        order_book.bump = ctx.bumps.order_book;
+
+        let pair_id_claim = &mut ctx.accounts.pair_id_claim;
+        pair_id_claim.trading_pair = trading_pair_key;
+        pair_id_claim.bump = ctx.bumps.pair_id_claim;
+
+        let pending_computation = &mut ctx.accounts.pending_computation;
+        pending_computation.trading_pair = trading_pair_key;
+        pending_computation.bump = ctx.bumps.pending_computation;
+        pending_computation.track(
+            ComputationKind::InitOrderBook,
+            computation_offset,
+            ctx.accounts.payer.key(),
+            Clock::get()?.slot,
+        );
 
         // Queue MPC computation to initialize empty order book
         let args = vec![Argument::PlaintextU128(mxe_nonce)];
+        let order_book_key = ctx.accounts.order_book.key();
+        let pending_computation_key = ctx.accounts.pending_computation.key();
+        let computation_account_key = ctx.accounts.computation_account.key();
 
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
@@ -72,7 +267,24 @@ pub mod conf_hide {
             computation_offset,
             args,
             None,
-            vec![InitOrderBookCallback::callback_ix(&[])],
+            vec![InitOrderBookCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: trading_pair_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: order_book_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: pending_computation_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: computation_account_key,
+                    is_writable: false,
+                },
+            ])],
         )?;
         Ok(())
     }
@@ -85,12 +297,24 @@ pub mod conf_hide {
     ) -> Result<()> {
         let order_book = match output {
             ComputationOutputs::Success(InitOrderBookOutput { field_0 }) => field_0,
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+            _ => {
+                let pending_computation = &mut ctx.accounts.pending_computation;
+                pending_computation.mark_failed(ctx.accounts.computation_account.key)?;
+                emit!(ComputationFailedEvent {
+                    trading_pair_id: ctx.accounts.trading_pair.trading_pair_id,
+                    computation_offset: pending_computation.computation_offset,
+                    kind: pending_computation.kind,
+                });
+                return Ok(());
+            }
         };
 
         let trading_pair = &mut ctx.accounts.trading_pair;
-        trading_pair.order_book = order_book.ciphertexts[0];
+        require!(trading_pair.book_locked, ErrorCode::OrderBookNotLocked);
+        ctx.accounts.order_book.ciphertexts = order_book.ciphertexts;
         trading_pair.order_book_nonce = order_book.nonce;
+        trading_pair.book_locked = false;
+        ctx.accounts.pending_computation.complete();
 
         emit!(TradingPairInitializedEvent {
             trading_pair_id: trading_pair.trading_pair_id,
@@ -109,7 +333,7 @@ pub mod conf_hide {
         encrypted_price: [u8; 32],
         encrypted_quantity: [u8; 32],
         encrypted_is_buy: [u8; 32],
-        encrypted_trader_id: [u8; 32],
+        escrow_amount: u64,
     ) -> Result<()> {
         // Validate trading pair ID matches the account
         require!(
@@ -121,9 +345,12 @@ pub mod conf_hide {
             ctx.accounts.trading_pair.is_active,
             ErrorCode::TradingPairInactive
         );
+        ctx.accounts
+            .trading_pair
+            .enter_session(Clock::get()?.slot, SessionAction::SubmitOrder)?;
 
-        // Note: We can't validate encrypted order parameters directly
-        // Validation will happen in the MPC circuit
+        // Encrypted price and quantity are validated by the submit_order circuit
+        // against the market parameters; rejections come back in the receipt
 
         // Balance validation: Check if user provided token accounts
         // This is a basic validation - full validation would need client-side checks
@@ -157,10 +384,102 @@ pub mod conf_hide {
         // TODO: For production, implement additional balance checks:
         // 1. Client-side balance validation before encryption
         // 2. MPC circuit to verify sufficient balance within encrypted computation
-        // 3. Reserve tokens during order submission to prevent double-spending
+
+        let trader_nonce = &mut ctx.accounts.trader_nonce;
+        trader_nonce.trader = ctx.accounts.payer.key();
+        trader_nonce.bump = ctx.bumps.trader_nonce;
+        trader_nonce.consume(client_nonce)?;
+
+        let trading_pair_key = ctx.accounts.trading_pair.key();
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.owner = ctx.accounts.payer.key();
+        user_account.trading_pair = trading_pair_key;
+        user_account.bump = ctx.bumps.user_account;
+        user_account.encryption_pubkey = ctx.accounts.encryption_key.pubkey;
+        // A referrer is kept until the trader clears it, so passing another
+        // one is an error rather than a silent switch
+        if let Some(referrer) = &ctx.accounts.referrer {
+            require!(
+                user_account.referrer == Pubkey::default()
+                    || user_account.referrer == referrer.key(),
+                ErrorCode::ReferrerAlreadySet
+            );
+            user_account.referrer = referrer.key();
+        }
+        let has_referrer = user_account.referrer != Pubkey::default();
+        let receipt_nonce = ctx.accounts.encryption_key.next_output_nonce()?;
+        let (audit, auditor_pubkey, auditor_nonce) = ctx
+            .accounts
+            .trading_pair
+            .audit_receiver(&ctx.accounts.mxe_account)?;
+
+        ctx.accounts
+            .trading_pair
+            .claim_computation_offset(&trading_pair_key, computation_offset)?;
+        let book_version = ctx.accounts.trading_pair.lock_book()?;
+        let clock = Clock::get()?;
+        let slot = ctx.accounts.trading_pair.order_stamp(clock.slot);
+        let timestamp = clock.unix_timestamp as u64;
+
+        let pending_computation = &mut ctx.accounts.pending_computation;
+        pending_computation.track(
+            ComputationKind::SubmitOrder,
+            computation_offset,
+            ctx.accounts.payer.key(),
+            clock.slot,
+        );
+        pending_computation.client_pubkey = client_pubkey;
+        pending_computation.client_nonce = client_nonce;
+        pending_computation.ciphertexts = [encrypted_price, encrypted_quantity, encrypted_is_buy];
+        pending_computation.timestamp = timestamp;
+        pending_computation.slot = slot;
+        pending_computation.has_referrer = has_referrer;
+
+        // Reserve tokens in the pair vault so they can be refunded if the
+        // submission fails; the order is escrowed with what the vault received
+        // after any transfer fee
+        let mut escrowed = 0;
+        let mut escrow_is_base = false;
+        if escrow_amount > 0 {
+            let (Some(escrow_source), Some(escrow_vault), Some(escrow_mint), Some(token_program)) = (
+                &ctx.accounts.escrow_source,
+                &ctx.accounts.escrow_vault,
+                &ctx.accounts.escrow_mint,
+                &ctx.accounts.token_program,
+            ) else {
+                return Err(ErrorCode::InvalidEscrowAccount.into());
+            };
+            let is_base = ctx.accounts.trading_pair.vault_is_base(&escrow_vault.key())?;
+            require!(
+                escrow_source.mint == escrow_vault.mint && escrow_mint.key() == escrow_vault.mint,
+                ErrorCode::InvalidEscrowAccount
+            );
+            require!(
+                escrow_source.owner == ctx.accounts.payer.key(),
+                ErrorCode::InvalidEscrowAccount
+            );
+
+            escrowed = transfer_checked_net(
+                token_program,
+                escrow_source.to_account_info(),
+                escrow_mint,
+                escrow_vault.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                &[],
+                escrow_amount,
+            )?;
+
+            let user_account = &mut ctx.accounts.user_account;
+            user_account.deposit(is_base, escrowed)?;
+            user_account.lock(is_base, escrowed)?;
+            escrow_is_base = is_base;
+
+            pending_computation.escrow_amount = escrowed;
+            pending_computation.escrow_vault = escrow_vault.key();
+            pending_computation.escrow_refund_account = escrow_source.key();
+        }
 
         // Prepare encrypted order arguments
-        let timestamp = Clock::get()?.unix_timestamp as u64;
         let args = vec![
             // Order data (encrypted by client)
             Argument::ArcisPubkey(client_pubkey),
@@ -168,12 +487,37 @@ pub mod conf_hide {
             Argument::EncryptedU64(encrypted_price),
             Argument::EncryptedU64(encrypted_quantity),
             Argument::EncryptedBool(encrypted_is_buy),
-            Argument::EncryptedU128(encrypted_trader_id),
+            // Order metadata supplied by the program
+            Argument::PlaintextU128(trader_id(&ctx.accounts.payer.key())),
             Argument::PlaintextU64(timestamp),
+            Argument::PlaintextU64(slot),
+            Argument::PlaintextU64(escrowed),
+            Argument::PlaintextBool(escrow_is_base),
+            Argument::PlaintextBool(has_referrer),
+            // Market parameters
+            Argument::PlaintextU64(ctx.accounts.trading_pair.tick_size),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.lot_size),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.min_order_size),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.max_order_price),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.max_order_quantity),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.price_scale),
+            Argument::PlaintextU64(MAX_ORDER_NOTIONAL),
             // Current order book
             Argument::PlaintextU128(ctx.accounts.trading_pair.order_book_nonce),
-            Argument::Account(ctx.accounts.trading_pair.key(), 8, 32), // order book data
+            OrderBook::argument(ctx.accounts.order_book.key()),
+            Argument::PlaintextU64(book_version),
+            // Receipt recipient: the trader's registered key
+            Argument::ArcisPubkey(ctx.accounts.encryption_key.pubkey),
+            Argument::PlaintextU128(receipt_nonce),
+            // Audit record recipient
+            Argument::PlaintextBool(audit),
+            Argument::ArcisPubkey(auditor_pubkey),
+            Argument::PlaintextU128(auditor_nonce),
         ];
+        let order_book_key = ctx.accounts.order_book.key();
+        let pending_computation_key = ctx.accounts.pending_computation.key();
+        let computation_account_key = ctx.accounts.computation_account.key();
+        let user_account_key = ctx.accounts.user_account.key();
 
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
@@ -182,7 +526,28 @@ pub mod conf_hide {
             computation_offset,
             args,
             None,
-            vec![SubmitOrderCallback::callback_ix(&[])],
+            vec![SubmitOrderCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: trading_pair_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: order_book_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: pending_computation_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: user_account_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: computation_account_key,
+                    is_writable: false,
+                },
+            ])],
         )?;
 
         Ok(())
@@ -194,22 +559,80 @@ pub mod conf_hide {
         ctx: Context<SubmitOrderCallback>,
         output: ComputationOutputs<SubmitOrderOutput>,
     ) -> Result<()> {
-        let updated_book = match output {
-            ComputationOutputs::Success(SubmitOrderOutput { field_0 }) => field_0,
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+        let (updated_book, computed_version, accepted, receipt, audit_record) = match output {
+            ComputationOutputs::Success(SubmitOrderOutput {
+                field_0:
+                    SubmitOrderOutputStruct0 {
+                        field_0: updated_book,
+                        field_1: computed_version,
+                        field_2: accepted,
+                        field_3: receipt,
+                        field_4: audit_record,
+                    },
+            }) => (
+                updated_book,
+                computed_version,
+                accepted,
+                receipt,
+                audit_record,
+            ),
+            _ => {
+                let pending_computation = &mut ctx.accounts.pending_computation;
+                pending_computation.mark_failed(ctx.accounts.computation_account.key)?;
+                emit!(ComputationFailedEvent {
+                    trading_pair_id: ctx.accounts.trading_pair.trading_pair_id,
+                    computation_offset: pending_computation.computation_offset,
+                    kind: pending_computation.kind,
+                });
+                return Ok(());
+            }
         };
 
         let trading_pair = &mut ctx.accounts.trading_pair;
-        trading_pair.order_book = updated_book.ciphertexts[0];
+        trading_pair.commit_book(computed_version)?;
+        ctx.accounts.order_book.ciphertexts = updated_book.ciphertexts;
         trading_pair.order_book_nonce = updated_book.nonce;
-        trading_pair.total_orders += 1;
+
+        let pending_computation = &mut ctx.accounts.pending_computation;
+        let user_account = &mut ctx.accounts.user_account;
+        if accepted {
+            trading_pair.total_orders += 1;
+            user_account.open_orders = user_account
+                .open_orders
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+        } else if pending_computation.escrow_amount > 0 {
+            // The book was full: the escrow goes back to the trader's free balance
+            let is_base = trading_pair.vault_is_base(&pending_computation.escrow_vault)?;
+            user_account.unlock(is_base, pending_computation.escrow_amount)?;
+        }
+        pending_computation.complete();
 
         emit!(OrderSubmittedEvent {
             trading_pair_id: trading_pair.trading_pair_id,
+            auction_epoch: trading_pair.auction_epoch(pending_computation.slot),
             order_book_nonce: updated_book.nonce,
             total_orders: trading_pair.total_orders,
+            book_version: trading_pair.book_version,
+            accepted,
+        });
+
+        emit!(OrderReceiptEvent {
+            trading_pair_id: trading_pair.trading_pair_id,
+            trader: user_account.owner,
+            encryption_key: receipt.encryption_key,
+            nonce: receipt.nonce,
+            ciphertexts: receipt.ciphertexts,
         });
 
+        emit_audit_record(
+            trading_pair,
+            ComputationKind::SubmitOrder,
+            audit_record.encryption_key,
+            audit_record.nonce,
+            &audit_record.ciphertexts,
+        );
+
         Ok(())
     }
 
@@ -221,7 +644,6 @@ pub mod conf_hide {
         client_pubkey: [u8; 32],
         client_nonce: u128,
         encrypted_order_id: [u8; 32],
-        encrypted_trader_id: [u8; 32],
     ) -> Result<()> {
         // Validate trading pair ID matches the account
         require!(
@@ -233,6 +655,30 @@ pub mod conf_hide {
             ctx.accounts.trading_pair.is_active,
             ErrorCode::TradingPairInactive
         );
+        ctx.accounts
+            .trading_pair
+            .enter_session(Clock::get()?.slot, SessionAction::CancelOrder)?;
+
+        let trader_nonce = &mut ctx.accounts.trader_nonce;
+        trader_nonce.trader = ctx.accounts.payer.key();
+        trader_nonce.bump = ctx.bumps.trader_nonce;
+        trader_nonce.consume(client_nonce)?;
+
+        let (audit, auditor_pubkey, auditor_nonce) = ctx
+            .accounts
+            .trading_pair
+            .audit_receiver(&ctx.accounts.mxe_account)?;
+        let trading_pair_key = ctx.accounts.trading_pair.key();
+        ctx.accounts
+            .trading_pair
+            .claim_computation_offset(&trading_pair_key, computation_offset)?;
+        let book_version = ctx.accounts.trading_pair.lock_book()?;
+        ctx.accounts.pending_computation.track(
+            ComputationKind::CancelOrder,
+            computation_offset,
+            ctx.accounts.payer.key(),
+            Clock::get()?.slot,
+        );
 
         // Prepare encrypted cancellation arguments
         let args = vec![
@@ -240,11 +686,21 @@ pub mod conf_hide {
             Argument::ArcisPubkey(client_pubkey),
             Argument::PlaintextU128(client_nonce),
             Argument::EncryptedU128(encrypted_order_id),
-            Argument::EncryptedU128(encrypted_trader_id),
+            // Only the signer's own orders can be cancelled
+            Argument::PlaintextU128(trader_id(&ctx.accounts.payer.key())),
             // Current order book
             Argument::PlaintextU128(ctx.accounts.trading_pair.order_book_nonce),
-            Argument::Account(ctx.accounts.trading_pair.key(), 8, 32), // order book data
+            OrderBook::argument(ctx.accounts.order_book.key()),
+            Argument::PlaintextU64(book_version),
+            // Audit record recipient
+            Argument::PlaintextBool(audit),
+            Argument::ArcisPubkey(auditor_pubkey),
+            Argument::PlaintextU128(auditor_nonce),
         ];
+        let order_book_key = ctx.accounts.order_book.key();
+        let pending_computation_key = ctx.accounts.pending_computation.key();
+        let computation_account_key = ctx.accounts.computation_account.key();
+        let user_account_key = ctx.accounts.user_account.key();
 
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
@@ -253,7 +709,28 @@ pub mod conf_hide {
             computation_offset,
             args,
             None,
-            vec![CancelOrderCallback::callback_ix(&[])],
+            vec![CancelOrderCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: trading_pair_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: order_book_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: pending_computation_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: user_account_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: computation_account_key,
+                    is_writable: false,
+                },
+            ])],
         )?;
 
         Ok(())
@@ -265,24 +742,77 @@ pub mod conf_hide {
         ctx: Context<CancelOrderCallback>,
         output: ComputationOutputs<CancelOrderOutput>,
     ) -> Result<()> {
-        let updated_book = match output {
-            ComputationOutputs::Success(CancelOrderOutput { field_0 }) => field_0,
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+        let (updated_book, computed_version, cancelled, escrow, is_buy, audit_record) = match output
+        {
+            ComputationOutputs::Success(CancelOrderOutput {
+                field_0:
+                    CancelOrderOutputStruct0 {
+                        field_0: updated_book,
+                        field_1: computed_version,
+                        field_2: cancelled,
+                        field_3: escrow,
+                        field_4: is_buy,
+                        field_5: audit_record,
+                    },
+            }) => (
+                updated_book,
+                computed_version,
+                cancelled,
+                escrow,
+                is_buy,
+                audit_record,
+            ),
+            _ => {
+                let pending_computation = &mut ctx.accounts.pending_computation;
+                pending_computation.mark_failed(ctx.accounts.computation_account.key)?;
+                emit!(ComputationFailedEvent {
+                    trading_pair_id: ctx.accounts.trading_pair.trading_pair_id,
+                    computation_offset: pending_computation.computation_offset,
+                    kind: pending_computation.kind,
+                });
+                return Ok(());
+            }
         };
 
         let trading_pair = &mut ctx.accounts.trading_pair;
-        trading_pair.order_book = updated_book.ciphertexts[0];
+        trading_pair.commit_book(computed_version)?;
+        ctx.accounts.order_book.ciphertexts = updated_book.ciphertexts;
         trading_pair.order_book_nonce = updated_book.nonce;
+        ctx.accounts.pending_computation.complete();
+
+        if cancelled {
+            // Buy orders escrow quote tokens, sell orders escrow base tokens
+            let user_account = &mut ctx.accounts.user_account;
+            user_account.open_orders = user_account.open_orders.saturating_sub(1);
+            user_account.unlock(!is_buy, escrow)?;
+        }
 
         emit!(OrderCancelledEvent {
             trading_pair_id: trading_pair.trading_pair_id,
             order_book_nonce: updated_book.nonce,
+            book_version: trading_pair.book_version,
+            cancelled,
         });
 
+        emit_audit_record(
+            trading_pair,
+            ComputationKind::CancelOrder,
+            audit_record.encryption_key,
+            audit_record.nonce,
+            &audit_record.ciphertexts,
+        );
+
         Ok(())
     }
 
     /// Match orders in the trading pair (batch auction)
+    ///
+    /// The whole book is matched. The fills are kept MXE-encrypted in a
+    /// MatchFills account the cranker pays for, and each trader in them
+    /// receives and settles their share with `settle_fills`. The account's
+    /// rent and the cranker's bond go back to the cranker once every trader
+    /// has settled; when nothing traded the rent is returned at the callback
+    /// and the bond is forfeited.
     pub fn match_orders(
         ctx: Context<MatchOrders>,
         computation_offset: u64,
@@ -299,14 +829,114 @@ pub mod conf_hide {
             ErrorCode::TradingPairInactive
         );
 
-        let timestamp = Clock::get()?.unix_timestamp as u64;
-        let args = vec![
+        let clock = Clock::get()?;
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        trading_pair.enter_session(clock.slot, SessionAction::MatchOrders)?;
+        require!(
+            trading_pair.may_match(clock.slot),
+            ErrorCode::MatchRateLimited
+        );
+        let previous_auction_epoch = trading_pair.last_auction_epoch;
+        let order_cutoff = trading_pair.match_cutoff(clock.slot)?;
+        let auction_epoch = trading_pair.auction_epoch(order_cutoff.saturating_sub(1));
+
+        let trading_pair_key = ctx.accounts.trading_pair.key();
+        ctx.accounts
+            .trading_pair
+            .claim_computation_offset(&trading_pair_key, computation_offset)?;
+        let book_version = ctx.accounts.trading_pair.lock_book()?;
+        let pending_computation = &mut ctx.accounts.pending_computation;
+        pending_computation.track(
+            ComputationKind::MatchOrders,
+            computation_offset,
+            ctx.accounts.payer.key(),
+            clock.slot,
+        );
+        pending_computation.auction_epoch = auction_epoch;
+        pending_computation.previous_auction_epoch = previous_auction_epoch;
+
+        // The cranker's bond is held in the MatchFills account and returned
+        // with its rent unless the match produces no trades
+        let match_bond = ctx.accounts.trading_pair.match_bond;
+        if match_bond > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.match_fills.to_account_info(),
+            };
+            let cpi_ctx =
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, match_bond)?;
+        }
+
+        // The crank reward is paid once the match has settled, to the
+        // cranker's account if they gave one
+        let trading_pair = &ctx.accounts.trading_pair;
+        let reward_account = ctx
+            .accounts
+            .crank_reward_account
+            .as_ref()
+            .map_or(trading_pair.fee_vault, |account| account.key());
+        let match_fills = &mut ctx.accounts.match_fills;
+        match_fills.trading_pair = trading_pair_key;
+        match_fills.sequence = trading_pair.match_count;
+        match_fills.cranker = ctx.accounts.payer.key();
+        match_fills.crank_reward_account = reward_account;
+        match_fills.bond = match_bond;
+        match_fills.recorded = false;
+        match_fills.participants = [0; MATCH_PARTICIPANTS];
+        match_fills.settled = 0;
+        match_fills.quote_paid = 0;
+        match_fills.quote_received = 0;
+        match_fills.fees = 0;
+        match_fills.rebates = 0;
+        match_fills.referrals = 0;
+        match_fills.credit_reserves = 0;
+        match_fills.nonce = 0;
+        match_fills.ciphertexts = [[0; 32]; MATCH_FILLS_CIPHERTEXTS];
+        match_fills.bump = ctx.bumps.match_fills;
+
+        let disclose = trading_pair.disclosure_policy == DisclosurePolicy::PriceAndSize;
+        let timestamp = clock.unix_timestamp as u64;
+        let mut args = vec![
             // Current order book
-            Argument::PlaintextU128(ctx.accounts.trading_pair.order_book_nonce),
-            Argument::Account(ctx.accounts.trading_pair.key(), 8, 32),
+            Argument::PlaintextU128(trading_pair.order_book_nonce),
+            OrderBook::argument(ctx.accounts.order_book.key()),
             // Timestamp for trades
             Argument::PlaintextU64(timestamp),
+            Argument::PlaintextU64(order_cutoff),
+            Argument::PlaintextU64(book_version),
+            Argument::PlaintextBool(disclose),
+            Argument::PlaintextU64(trading_pair.price_scale),
+            Argument::PlaintextU64(trading_pair.lot_size),
+            Argument::PlaintextU64(trading_pair.maker_fee_bps as u64),
+            Argument::PlaintextU64(trading_pair.taker_fee_bps as u64),
+            Argument::PlaintextU64(trading_pair.maker_rebate_bps as u64),
+            Argument::PlaintextU64(trading_pair.referral_share_bps as u64),
         ];
+        let (audit, auditor_pubkey, auditor_nonce) = ctx
+            .accounts
+            .trading_pair
+            .audit_receiver(&ctx.accounts.mxe_account)?;
+        args.push(Argument::PlaintextBool(audit));
+        args.push(Argument::ArcisPubkey(auditor_pubkey));
+        args.push(Argument::PlaintextU128(auditor_nonce));
+        // Fills for settle_fills, under a nonce no other output shares
+        args.push(Argument::PlaintextU128(fills_nonce(
+            &trading_pair_key,
+            computation_offset,
+        )));
+        // Trade prints for the delayed tape
+        args.push(Argument::PlaintextU128(tape_nonce(
+            &trading_pair_key,
+            computation_offset,
+        )));
+        let order_book_key = ctx.accounts.order_book.key();
+        let pending_computation_key = ctx.accounts.pending_computation.key();
+        let computation_account_key = ctx.accounts.computation_account.key();
+        let trade_tape_key = ctx.accounts.trade_tape.key();
+        let match_fills_key = ctx.accounts.match_fills.key();
+        let payer_key = ctx.accounts.payer.key();
+        let authority_key = ctx.accounts.trading_pair.authority;
 
         ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
@@ -315,7 +945,40 @@ pub mod conf_hide {
             computation_offset,
             args,
             None,
-            vec![MatchOrdersCallback::callback_ix(&[])],
+            vec![MatchOrdersCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: trading_pair_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: order_book_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: pending_computation_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: trade_tape_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: computation_account_key,
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: match_fills_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: payer_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: authority_key,
+                    is_writable: true,
+                },
+            ])],
         )?;
 
         Ok(())
@@ -327,210 +990,4364 @@ pub mod conf_hide {
         ctx: Context<MatchOrdersCallback>,
         output: ComputationOutputs<MatchOrdersOutput>,
     ) -> Result<()> {
-        let match_result = match output {
+        let output = match output {
             ComputationOutputs::Success(MatchOrdersOutput { field_0 }) => field_0,
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+            _ => {
+                let pending_computation = &mut ctx.accounts.pending_computation;
+                pending_computation.mark_failed(ctx.accounts.computation_account.key)?;
+                emit!(ComputationFailedEvent {
+                    trading_pair_id: ctx.accounts.trading_pair.trading_pair_id,
+                    computation_offset: pending_computation.computation_offset,
+                    kind: pending_computation.kind,
+                });
+                ctx.accounts
+                    .match_fills
+                    .close(ctx.accounts.cranker.to_account_info())?;
+                return Ok(());
+            }
         };
+        let MatchOrdersOutputStruct0 {
+            field_0: updated_book,
+            field_1: computed_version,
+            field_2: summary,
+            field_3: disclosure,
+            field_4: fills,
+            field_5: audit_record,
+            field_6: tape_batch,
+        } = output;
 
         // Extract trade data and updated order book from MPC result
         let trading_pair = &mut ctx.accounts.trading_pair;
-        trading_pair.order_book_nonce = match_result.nonce;
-
-        // TODO: For production implementation, need to:
-        // 1. Deserialize MatchResult from match_result.ciphertexts
-        // 2. Extract individual trades from the result
-        // 3. For each trade, execute token transfers
-        // 4. Handle partial fills and order book updates
+        trading_pair.commit_book(computed_version)?;
+        ctx.accounts.order_book.ciphertexts = updated_book.ciphertexts;
+        trading_pair.order_book_nonce = updated_book.nonce;
+        ctx.accounts.pending_computation.complete();
 
-        // Current limitation: MPC results are encrypted and need decryption
-        // For MVP, we emit a placeholder event showing the computation completed
         emit!(OrdersMatchedEvent {
             trading_pair_id: trading_pair.trading_pair_id,
-            match_nonce: match_result.nonce,
+            auction_epoch: ctx.accounts.pending_computation.auction_epoch,
+            match_nonce: updated_book.nonce,
             timestamp: Clock::get()?.unix_timestamp as u64,
+            book_version: trading_pair.book_version,
         });
 
-        // In a complete implementation, we would extract trades like this:
-        // let trades = deserialize_trades_from_mpc_result(&match_result);
-        // for trade in trades {
-        //     execute_individual_trade(ctx, trade)?;
-        // }
-
-        Ok(())
-    }
+        if trading_pair.disclosure_policy == DisclosurePolicy::PriceAndSize {
+            emit!(TradesDisclosedEvent {
+                trading_pair_id: trading_pair.trading_pair_id,
+                book_version: trading_pair.book_version,
+                prices: disclosure.field_0,
+                quantities: disclosure.field_1,
+                trade_count: disclosure.field_2,
+            });
+        }
 
-    /// Execute token transfers for matched trades
-    /// Called after MPC reveals matched trades
-    pub fn execute_trade(
-        ctx: Context<ExecuteTrade>,
-        buyer_id: u128,
-        seller_id: u128,
-        trade_price: u64,
-        trade_quantity: u64,
-    ) -> Result<()> {
-        // Validate trade parameters
-        require!(trade_price > 0, ErrorCode::InvalidPrice);
-        require!(trade_quantity > 0, ErrorCode::InvalidQuantity);
+        emit_audit_record(
+            trading_pair,
+            ComputationKind::MatchOrders,
+            audit_record.encryption_key,
+            audit_record.nonce,
+            &audit_record.ciphertexts,
+        );
 
-        // Deserialize and validate token accounts
-        let buyer_quote = TokenAccount::try_deserialize(&mut &ctx.accounts.buyer_quote_account.try_borrow_data()?[..])?;
-        let seller_base = TokenAccount::try_deserialize(&mut &ctx.accounts.seller_base_account.try_borrow_data()?[..])?;
-        let buyer_base = TokenAccount::try_deserialize(&mut &ctx.accounts.buyer_base_account.try_borrow_data()?[..])?;
-        let seller_quote = TokenAccount::try_deserialize(&mut &ctx.accounts.seller_quote_account.try_borrow_data()?[..])?;
+        // Nothing traded, so there is nothing to print or settle. The book is
+        // not matched again until it changes or the interval has passed, and
+        // the cranker's bond goes to the pair authority.
+        let trade_count = summary.field_0;
+        if trade_count == 0 {
+            trading_pair.last_empty_match_slot = Clock::get()?.slot;
+            trading_pair.empty_match_version = trading_pair.book_version;
+            let bond = ctx.accounts.match_fills.bond;
+            if bond > 0 {
+                ctx.accounts.match_fills.sub_lamports(bond)?;
+                ctx.accounts.authority.add_lamports(bond)?;
+                emit!(MatchBondForfeitedEvent {
+                    trading_pair_id: trading_pair.trading_pair_id,
+                    cranker: ctx.accounts.match_fills.cranker,
+                    amount: bond,
+                });
+            }
+            return ctx
+                .accounts
+                .match_fills
+                .close(ctx.accounts.cranker.to_account_info());
+        }
 
-        // Validate token accounts belong to the correct traders
-        require!(buyer_quote.owner == ctx.accounts.buyer.key(), ErrorCode::InvalidTokenAccount);
-        require!(seller_base.owner == ctx.accounts.seller.key(), ErrorCode::InvalidTokenAccount);
-        require!(buyer_base.owner == ctx.accounts.buyer.key(), ErrorCode::InvalidTokenAccount);
-        require!(seller_quote.owner == ctx.accounts.seller.key(), ErrorCode::InvalidTokenAccount);
+        // Matching never waits on reveals; the oldest entry gives way
+        let trade_tape = &mut ctx.accounts.trade_tape;
+        let oldest = trade_tape.entry(trade_tape.next_sequence);
+        if oldest.pending {
+            emit!(TapeEntryDroppedEvent {
+                trading_pair_id: trading_pair.trading_pair_id,
+                sequence: oldest.sequence,
+            });
+        }
+        let sequence = trade_tape.record(
+            tape_batch.nonce,
+            tape_batch.ciphertexts,
+            Clock::get()?.unix_timestamp,
+        )?;
+        emit!(TapeRecordedEvent {
+            trading_pair_id: trading_pair.trading_pair_id,
+            sequence,
+            reveal_after: trade_tape.entry(sequence).reveal_after,
+        });
 
-        // Calculate total quote amount (price * quantity)
-        let quote_amount = trade_price
-            .checked_mul(trade_quantity)
+        // Traders find their share by their ID and settle it with settle_fills
+        let match_fills = &mut ctx.accounts.match_fills;
+        match_fills.recorded = true;
+        match_fills.participants = summary.field_1;
+        match_fills.nonce = fills.nonce;
+        match_fills.ciphertexts = fills.ciphertexts;
+        trading_pair.match_count = trading_pair
+            .match_count
+            .checked_add(1)
             .ok_or(ErrorCode::MathOverflow)?;
+        emit!(MatchRecordedEvent {
+            trading_pair_id: trading_pair.trading_pair_id,
+            sequence: match_fills.sequence,
+            trade_count,
+            participants: match_fills.participants,
+        });
 
-        // Verify sufficient balances before executing transfers
+        Ok(())
+    }
+
+    /// Settle one trader's share of a recorded match: their fills are
+    /// encrypted to their registered key, the escrow of their closed orders is
+    /// released and, under a disclosing policy, the fills are applied to their
+    /// user account. Anyone may crank it for any trader in the match. Once
+    /// all of them have settled, the match's fees are swept into the fee
+    /// vault and its MatchFills account is closed.
+    pub fn settle_fills(
+        ctx: Context<SettleFills>,
+        computation_offset: u64,
+        trading_pair_id: u64,
+        sequence: u64,
+    ) -> Result<()> {
         require!(
-            buyer_quote.amount >= quote_amount,
-            ErrorCode::InsufficientBalance
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
         );
+
+        let match_fills = &ctx.accounts.match_fills;
         require!(
-            seller_base.amount >= trade_quantity,
-            ErrorCode::InsufficientBalance
+            match_fills.recorded && match_fills.sequence == sequence,
+            ErrorCode::NotAMatchParticipant
         );
+        let trader = trader_id(&ctx.accounts.user_account.owner);
+        let index = match_fills
+            .participant_index(trader)
+            .ok_or(ErrorCode::NotAMatchParticipant)?;
+        require!(!match_fills.has_settled(index), ErrorCode::AlreadySettled);
 
-        // Transfer quote tokens from buyer to seller
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.buyer_quote_account.to_account_info(),
-            to: ctx.accounts.seller_quote_account.to_account_info(),
-            authority: ctx.accounts.buyer.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        // One settlement per trader at a time, as each rewrites their volume
+        let clock = Clock::get()?;
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.settle_queued_slot == 0
+                || clock.slot
+                    >= user_account
+                        .settle_queued_slot
+                        .saturating_add(COMPUTATION_TIMEOUT_SLOTS),
+            ErrorCode::SettlementInFlight
+        );
+        user_account.settle_offset = computation_offset;
+        user_account.settle_queued_slot = clock.slot;
 
-        let quote_amount = trade_price
-            .checked_mul(trade_quantity)
-            .ok_or(ErrorCode::MathOverflow)?;
+        let trading_pair = &ctx.accounts.trading_pair;
+        let disclose = trading_pair.disclosure_policy == DisclosurePolicy::PriceAndSize;
+        let mut args = match_fills.arguments();
+        args.push(Argument::PlaintextU128(trader));
+        args.push(Argument::PlaintextBool(disclose));
+        args.push(Argument::PlaintextU64(trading_pair.price_scale));
+        for tier in trading_pair.fee_tiers {
+            args.push(Argument::PlaintextU64(tier.min_volume));
+            args.push(Argument::PlaintextU64(tier.discount_bps as u64));
+        }
+        args.push(Argument::PlaintextBool(user_account.has_volume));
+        args.push(Argument::PlaintextU128(user_account.volume_nonce));
+        for ciphertext in user_account.volume_ciphertexts {
+            args.push(Argument::EncryptedU64(ciphertext));
+        }
+        let encryption_key = &mut ctx.accounts.encryption_key;
+        args.push(Argument::ArcisPubkey(encryption_key.pubkey));
+        args.push(Argument::PlaintextU128(encryption_key.next_output_nonce()?));
 
-        token::transfer(cpi_ctx, quote_amount)?;
+        // Under a disclosing policy the trader's referrer is credited, and the
+        // last settlement sweeps the fees out of the quote vault; under any
+        // policy it pays the crank reward if the cranker gave an account
+        let pays_reward = match_fills.crank_reward_account != trading_pair.fee_vault;
+        let mut callback_accounts = vec![
+            CallbackAccount {
+                pubkey: trading_pair.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: match_fills.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: user_account.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.computation_account.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: match_fills.cranker,
+                is_writable: true,
+            },
+        ];
+        if disclose && user_account.referrer != Pubkey::default() {
+            callback_accounts.push(CallbackAccount {
+                pubkey: user_account.referrer,
+                is_writable: true,
+            });
+        }
+        if disclose || pays_reward {
+            callback_accounts.extend([
+                CallbackAccount {
+                    pubkey: trading_pair.quote_vault,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: trading_pair.fee_vault,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: trading_pair.quote_mint,
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: trading_pair.quote_token_program,
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: match_fills.crank_reward_account,
+                    is_writable: true,
+                },
+            ]);
+        }
 
-        // Transfer base tokens from seller to buyer
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.seller_base_account.to_account_info(),
-            to: ctx.accounts.buyer_base_account.to_account_info(),
-            authority: ctx.accounts.seller.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let trading_pair_key = ctx.accounts.trading_pair.key();
+        ctx.accounts
+            .trading_pair
+            .claim_computation_offset(&trading_pair_key, computation_offset)?;
 
-        token::transfer(cpi_ctx, trade_quantity)?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
-        emit!(TradeExecutedEvent {
-            buyer_id,
-            seller_id,
-            price: trade_price,
-            quantity: trade_quantity,
-            timestamp: Clock::get()?.unix_timestamp as u64,
-        });
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SettleFillsCallback::callback_ix(&callback_accounts)],
+        )?;
 
         Ok(())
     }
-}
 
-/// Trading pair account storing encrypted order book state
-#[account]
-#[derive(InitSpace)]
-pub struct TradingPair {
-    /// Unique identifier for this trading pair
-    pub trading_pair_id: u64,
-    /// Base token mint (e.g., SOL)
-    pub base_mint: Pubkey,
-    /// Quote token mint (e.g., USDC)
-    pub quote_mint: Pubkey,
-    /// Encrypted order book data
-    pub order_book: [u8; 32],
-    /// Nonce for order book encryption
-    pub order_book_nonce: u128,
-    /// Whether trading is active
-    pub is_active: bool,
-    /// Total orders submitted
-    pub total_orders: u64,
-    /// PDA bump
-    pub bump: u8,
-}
+    /// Callback handler for settling a trader's fills
+    #[arcium_callback(encrypted_ix = "settle_fills")]
+    pub fn settle_fills_callback<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleFillsCallback<'info>>,
+        output: ComputationOutputs<SettleFillsOutput>,
+    ) -> Result<()> {
+        // Only the trader's latest settlement may land; a failed one can be
+        // queued again straight away
+        let user_account = &mut ctx.accounts.user_account;
+        require_keys_eq!(
+            ctx.accounts.computation_account.key(),
+            derive_comp_pda!(user_account.settle_offset),
+            ErrorCode::ComputationMismatch
+        );
+        user_account.settle_queued_slot = 0;
+        let (report, revealed, volume) = match output {
+            ComputationOutputs::Success(SettleFillsOutput {
+                field_0:
+                    SettleFillsOutputStruct0 {
+                        field_0: report,
+                        field_1: settlement,
+                        field_2: volume,
+                    },
+            }) => (report, settlement, volume),
+            _ => return Ok(()),
+        };
+        let settlement = Settlement {
+            base_credit: revealed.field_0,
+            quote_credit: revealed.field_1,
+            base_debit: revealed.field_2,
+            quote_debit: revealed.field_3,
+            base_release: revealed.field_4,
+            quote_release: revealed.field_5,
+            fill_count: revealed.field_6,
+            orders_closed: revealed.field_7,
+            buy_fee: revealed.field_8,
+            sell_fee: revealed.field_9,
+            rebate: revealed.field_10,
+            referral: revealed.field_11,
+            credit_reserve: revealed.field_12,
+        };
 
-// Account validation structures for initialization
-#[queue_computation_accounts("init_order_book", payer)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64, trading_pair_id: u64)]
-pub struct InitializeTradingPair<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = payer,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
-    #[account(address = derive_mxe_pda!())]
-    pub mxe_account: Account<'info, MXEAccount>,
-    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
-    #[account(mut, address = derive_mempool_pda!())]
-    pub mempool_account: UncheckedAccount<'info>,
-    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
-    #[account(mut, address = derive_execpool_pda!())]
-    pub executing_pool: UncheckedAccount<'info>,
-    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
-    #[account(mut, address = derive_comp_pda!(computation_offset))]
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ORDER_BOOK))]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(mut, address = derive_cluster_pda!(mxe_account))]
-    pub cluster_account: Account<'info, Cluster>,
-    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
-    pub pool_account: Account<'info, FeePool>,
-    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
-    pub clock_account: Account<'info, ClockAccount>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + TradingPair::INIT_SPACE,
-        seeds = [b"trading_pair", trading_pair_id.to_le_bytes().as_ref()],
-        bump,
-    )]
-    pub trading_pair: Account<'info, TradingPair>,
-    /// CHECK: Base token mint address, validated by trading_pair.base_mint
-    pub base_mint: UncheckedAccount<'info>,
-    /// CHECK: Quote token mint address, validated by trading_pair.quote_mint
-    pub quote_mint: UncheckedAccount<'info>,
-}
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        let match_fills = &mut ctx.accounts.match_fills;
+        let index = match_fills
+            .participant_index(trader_id(&user_account.owner))
+            .ok_or(ErrorCode::NotAMatchParticipant)?;
+        require!(!match_fills.has_settled(index), ErrorCode::AlreadySettled);
+        match_fills.settled |= 1 << index;
 
-// Callback account structure
-#[callback_accounts("init_order_book")]
-#[derive(Accounts)]
-pub struct InitOrderBookCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
+        // The trader finds their report by the key it was encrypted to
+        emit!(FillReportEvent {
+            trading_pair_id: trading_pair.trading_pair_id,
+            sequence: match_fills.sequence,
+            encryption_key: report.encryption_key,
+            nonce: report.nonce,
+            ciphertexts: report.ciphertexts,
+        });
 
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ORDER_BOOK))]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: Validated by Arcium program through address constraint
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub trading_pair: Account<'info, TradingPair>,
-}
+        user_account.has_volume = true;
+        user_account.volume_nonce = volume.nonce;
+        user_account.volume_ciphertexts = volume.ciphertexts;
+
+        // Under a disclosing policy fills settle between user accounts inside
+        // the pair vaults, so no transfer links a buyer to a seller; a private
+        // settlement only releases the escrow of closed orders. The trader's
+        // referrer, if any, comes first among the remaining accounts.
+        let disclose = trading_pair.disclosure_policy == DisclosurePolicy::PriceAndSize;
+        user_account.settle(&settlement)?;
+        let mut infos = ctx.remaining_accounts;
+        if disclose {
+            if user_account.referrer != Pubkey::default() {
+                let (info, rest) = infos
+                    .split_first()
+                    .ok_or(ErrorCode::InvalidSettlementAccounts)?;
+                require_keys_eq!(
+                    info.key(),
+                    user_account.referrer,
+                    ErrorCode::InvalidReferrer
+                );
+                let mut referrer = Account::<Referrer>::try_from(info)?;
+                referrer.accrue(settlement.referral)?;
+                referrer.exit(&crate::ID)?;
+                match_fills.referrals = match_fills
+                    .referrals
+                    .checked_add(settlement.referral)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                infos = rest;
+            }
+            match_fills.accumulate(&settlement)?;
+            // Fees are charged at base rates; the trader's discount is held
+            // back in the quote vault until they claim it
+            user_account.fee_credit_reserve = user_account
+                .fee_credit_reserve
+                .checked_add(settlement.credit_reserve)
+                .ok_or(ErrorCode::MathOverflow)?;
+            trading_pair.fee_credit_reserved = trading_pair
+                .fee_credit_reserved
+                .checked_add(settlement.credit_reserve)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        if !match_fills.is_complete() {
+            return Ok(());
+        }
+
+        let (fees, referrals, credits) = if disclose {
+            // Every trader in the match has settled, so the debits and credits
+            // cover each trade once and their gap is the dust
+            let dust = match_fills
+                .quote_paid
+                .checked_sub(match_fills.quote_received)
+                .ok_or(ErrorCode::MathOverflow)?;
+            trading_pair.accrue_dust(dust)?;
+
+            // Rebates stay in the quote vault, credited to the makers; the
+            // circuit caps each by its taker fee. Referral shares move to the
+            // fee vault with the fees and are held there for their referrers.
+            // Discount reserves stay in the quote vault for their traders.
+            let referrals = match_fills.referrals;
+            let credits = match_fills.credit_reserves;
+            let fees = match_fills
+                .fees
+                .checked_sub(match_fills.rebates)
+                .ok_or(ErrorCode::RebateExceedsFees)?;
+            require!(
+                referrals
+                    .checked_add(credits)
+                    .is_some_and(|payouts| payouts <= fees),
+                ErrorCode::RebateExceedsFees
+            );
+            trading_pair.referral_owed = trading_pair
+                .referral_owed
+                .checked_add(referrals)
+                .ok_or(ErrorCode::MathOverflow)?;
+            (fees, referrals, credits)
+        } else {
+            (0, 0, 0)
+        };
+
+        // Only matches that produced trades are recorded, so the crank reward
+        // is paid on every productive match. Under a disclosing policy it
+        // comes out of the fees the match earned beyond the referral shares;
+        // private fills pay no fee, so there it comes out of what the fee
+        // vault holds beyond the shares owed to referrers.
+        let (fees, reward) = if infos.is_empty() {
+            (0, 0)
+        } else {
+            require!(infos.len() == 5, ErrorCode::InvalidSettlementAccounts);
+            let reward_account = &infos[4];
+            require_keys_eq!(
+                reward_account.key(),
+                match_fills.crank_reward_account,
+                ErrorCode::InvalidSettlementAccounts
+            );
+            if disclose {
+                let reward = if reward_account.key() != trading_pair.fee_vault {
+                    trading_pair.crank_reward.min(fees - referrals - credits)
+                } else {
+                    0
+                };
+                (fees - reward - credits, reward)
+            } else {
+                let fee_vault = InterfaceAccount::<TokenAccount>::try_from(&infos[1])?;
+                require_keys_eq!(
+                    fee_vault.key(),
+                    trading_pair.fee_vault,
+                    ErrorCode::InvalidEscrowAccount
+                );
+                let available = fee_vault.amount.saturating_sub(trading_pair.referral_owed);
+                (0, trading_pair.crank_reward.min(available))
+            }
+        };
+
+        if fees > 0 || reward > 0 {
+            let quote_vault = &infos[0];
+            let fee_vault = &infos[1];
+            require!(
+                quote_vault.key() == trading_pair.quote_vault
+                    && fee_vault.key() == trading_pair.fee_vault,
+                ErrorCode::InvalidEscrowAccount
+            );
+            let quote_mint = InterfaceAccount::<Mint>::try_from(&infos[2])?;
+            let token_program = Interface::<TokenInterface>::try_from(&infos[3])?;
+            require_keys_eq!(
+                quote_mint.key(),
+                trading_pair.quote_mint,
+                ErrorCode::InvalidTokenAccount
+            );
+
+            let [low_mint, high_mint] = trading_pair.seed_mints();
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"trading_pair",
+                low_mint.as_ref(),
+                high_mint.as_ref(),
+                &[trading_pair.bump],
+            ]];
+            if fees > 0 {
+                let collected = transfer_checked_net(
+                    &token_program,
+                    quote_vault.clone(),
+                    &quote_mint,
+                    fee_vault.clone(),
+                    trading_pair.to_account_info(),
+                    signer_seeds,
+                    fees,
+                )?;
+                trading_pair.collect_fees(collected)?;
+            }
+            if reward > 0 {
+                let reward_account = &infos[4];
+                let source = if disclose { quote_vault } else { fee_vault };
+                transfer_checked_net(
+                    &token_program,
+                    source.clone(),
+                    &quote_mint,
+                    reward_account.clone(),
+                    trading_pair.to_account_info(),
+                    signer_seeds,
+                    reward,
+                )?;
+                emit!(CrankRewardedEvent {
+                    trading_pair_id: trading_pair.trading_pair_id,
+                    cranker: match_fills.cranker,
+                    reward_account: reward_account.key(),
+                    amount: reward,
+                });
+            }
+        }
+
+        ctx.accounts
+            .match_fills
+            .close(ctx.accounts.cranker.to_account_info())
+    }
+
+    /// Claim the tier discounts the signer earned on this pair's fees. The MXE
+    /// reveals the discount accrued since their last claim, which joins their
+    /// free quote balance; the rest of what was held back for it goes to the
+    /// fee vault.
+    pub fn claim_fee_credit(
+        ctx: Context<ClaimFeeCredit>,
+        computation_offset: u64,
+        trading_pair_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+
+        // Shares the settlement guard, as both rewrite the trader's volume
+        let clock = Clock::get()?;
+        let user_account = &mut ctx.accounts.user_account;
+        require!(user_account.fee_credit_reserve > 0, ErrorCode::NoFeeCredit);
+        require!(
+            user_account.settle_queued_slot == 0
+                || clock.slot
+                    >= user_account
+                        .settle_queued_slot
+                        .saturating_add(COMPUTATION_TIMEOUT_SLOTS),
+            ErrorCode::SettlementInFlight
+        );
+        user_account.settle_offset = computation_offset;
+        user_account.settle_queued_slot = clock.slot;
+
+        let mut args = vec![Argument::PlaintextU128(user_account.volume_nonce)];
+        for ciphertext in user_account.volume_ciphertexts {
+            args.push(Argument::EncryptedU64(ciphertext));
+        }
+        let trading_pair = &ctx.accounts.trading_pair;
+        let callback_accounts = [
+            CallbackAccount {
+                pubkey: trading_pair.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: user_account.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.computation_account.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: trading_pair.quote_vault,
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: trading_pair.fee_vault,
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: trading_pair.quote_mint,
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: trading_pair.quote_token_program,
+                is_writable: false,
+            },
+        ];
+
+        let trading_pair_key = ctx.accounts.trading_pair.key();
+        ctx.accounts
+            .trading_pair
+            .claim_computation_offset(&trading_pair_key, computation_offset)?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ClaimFeeCreditCallback::callback_ix(&callback_accounts)],
+        )?;
+
+        Ok(())
+    }
+
+    /// Callback handler for claiming a trader's fee credit
+    #[arcium_callback(encrypted_ix = "claim_fee_credit")]
+    pub fn claim_fee_credit_callback(
+        ctx: Context<ClaimFeeCreditCallback>,
+        output: ComputationOutputs<ClaimFeeCreditOutput>,
+    ) -> Result<()> {
+        let user_account = &mut ctx.accounts.user_account;
+        require_keys_eq!(
+            ctx.accounts.computation_account.key(),
+            derive_comp_pda!(user_account.settle_offset),
+            ErrorCode::ComputationMismatch
+        );
+        user_account.settle_queued_slot = 0;
+        let (credit, volume) = match output {
+            ComputationOutputs::Success(ClaimFeeCreditOutput {
+                field_0:
+                    ClaimFeeCreditOutputStruct0 {
+                        field_0: credit,
+                        field_1: volume,
+                    },
+            }) => (credit, volume),
+            _ => return Ok(()),
+        };
+        user_account.volume_nonce = volume.nonce;
+        user_account.volume_ciphertexts = volume.ciphertexts;
+
+        // The reserve was taken at the highest tier's rate, so it covers the
+        // credit and the difference is the pair's fee
+        let reserve = user_account.fee_credit_reserve;
+        let credit = credit.min(reserve);
+        user_account.fee_credit_reserve = 0;
+        user_account.deposit(false, credit)?;
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        trading_pair.fee_credit_reserved = trading_pair.fee_credit_reserved.saturating_sub(reserve);
+
+        let surplus = reserve - credit;
+        if surplus > 0 {
+            let [low_mint, high_mint] = trading_pair.seed_mints();
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"trading_pair",
+                low_mint.as_ref(),
+                high_mint.as_ref(),
+                &[trading_pair.bump],
+            ]];
+            let collected = transfer_checked_net(
+                &ctx.accounts.quote_token_program,
+                ctx.accounts.quote_vault.to_account_info(),
+                &ctx.accounts.quote_mint,
+                ctx.accounts.fee_vault.to_account_info(),
+                trading_pair.to_account_info(),
+                signer_seeds,
+                surplus,
+            )?;
+            trading_pair.collect_fees(collected)?;
+        }
+
+        emit!(FeeCreditClaimedEvent {
+            trading_pair_id: trading_pair.trading_pair_id,
+            owner: user_account.owner,
+            amount: credit,
+        });
+        Ok(())
+    }
+
+    /// Ask the MXE for a page of the signer's resting orders, encrypted to their
+    /// registered key. The book is read but not locked or rewritten.
+    pub fn query_my_orders(
+        ctx: Context<QueryMyOrders>,
+        computation_offset: u64,
+        trading_pair_id: u64,
+        page: u8,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(page < MAX_ORDER_PAGES, ErrorCode::InvalidOrderPage);
+
+        let trading_pair_key = ctx.accounts.trading_pair.key();
+        ctx.accounts
+            .trading_pair
+            .claim_computation_offset(&trading_pair_key, computation_offset)?;
+
+        let receiver_nonce = ctx.accounts.encryption_key.next_output_nonce()?;
+        let args = vec![
+            Argument::PlaintextU128(trader_id(&ctx.accounts.payer.key())),
+            Argument::PlaintextU8(page),
+            // Current order book
+            Argument::PlaintextU128(ctx.accounts.trading_pair.order_book_nonce),
+            OrderBook::argument(ctx.accounts.order_book.key()),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.book_version),
+            // Result goes to the signer's registered key
+            Argument::ArcisPubkey(ctx.accounts.encryption_key.pubkey),
+            Argument::PlaintextU128(receiver_nonce),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![QueryMyOrdersCallback::callback_ix(&[CallbackAccount {
+                pubkey: trading_pair_key,
+                is_writable: false,
+            }])],
+        )?;
+
+        Ok(())
+    }
+
+    /// Callback handler for order queries
+    #[arcium_callback(encrypted_ix = "query_my_orders")]
+    pub fn query_my_orders_callback(
+        ctx: Context<QueryMyOrdersCallback>,
+        output: ComputationOutputs<QueryMyOrdersOutput>,
+    ) -> Result<()> {
+        let (computed_version, my_orders) = match output {
+            ComputationOutputs::Success(QueryMyOrdersOutput {
+                field_0:
+                    QueryMyOrdersOutputStruct0 {
+                        field_0: computed_version,
+                        field_1: my_orders,
+                    },
+            }) => (computed_version, my_orders),
+            _ => return Err(ErrorCode::AbortedComputation.into()),
+        };
+
+        // A rewrite since queueing means the book was read with a stale nonce
+        let trading_pair = &ctx.accounts.trading_pair;
+        require!(
+            computed_version == trading_pair.book_version,
+            ErrorCode::StaleOrderBook
+        );
+
+        emit!(MyOrdersEvent {
+            trading_pair_id: trading_pair.trading_pair_id,
+            book_version: computed_version,
+            encryption_key: my_orders.encryption_key,
+            nonce: my_orders.nonce,
+            ciphertexts: my_orders.ciphertexts,
+        });
+
+        Ok(())
+    }
+
+    /// Enable or tune bucketed depth disclosure for a trading pair
+    pub fn configure_depth(
+        ctx: Context<ConfigureDepth>,
+        trading_pair_id: u64,
+        enabled: bool,
+        band_width: u64,
+        lot_size: u64,
+        min_orders: u8,
+        interval_slots: u64,
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        if enabled {
+            // The circuit computes band bounds up to DEPTH_BANDS * band_width
+            require!(
+                band_width > 0 && lot_size > 0 && band_width.checked_mul(DEPTH_BANDS).is_some(),
+                ErrorCode::InvalidDepthConfig
+            );
+            // A band must aggregate at least two orders to be shown
+            require!(min_orders >= 2, ErrorCode::InvalidDepthConfig);
+        }
+
+        trading_pair.depth_enabled = enabled;
+        trading_pair.depth_band_width = band_width;
+        trading_pair.depth_lot_size = lot_size;
+        trading_pair.depth_min_orders = min_orders;
+        trading_pair.depth_interval_slots = interval_slots;
+        Ok(())
+    }
+
+    /// Set the maker and taker fee rates of a trading pair, in basis points.
+    /// A maker rebate acts as a negative maker fee, so it excludes a maker
+    /// fee and cannot exceed the taker fee it is paid from. Private pairs do
+    /// not settle matched fills on-chain, so there the rates only apply to
+    /// trades settled through execute_trade.
+    pub fn configure_fees(
+        ctx: Context<ConfigureFees>,
+        trading_pair_id: u64,
+        maker_fee_bps: u16,
+        taker_fee_bps: u16,
+        maker_rebate_bps: u16,
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(
+            maker_fee_bps <= MAX_FEE_BPS && taker_fee_bps <= MAX_FEE_BPS,
+            ErrorCode::InvalidFeeRate
+        );
+        require!(
+            maker_rebate_bps == 0 || maker_fee_bps == 0,
+            ErrorCode::InvalidFeeRate
+        );
+
+        trading_pair.maker_fee_bps = maker_fee_bps;
+        trading_pair.taker_fee_bps = taker_fee_bps;
+        trading_pair.maker_rebate_bps = maker_rebate_bps;
+        require!(
+            trading_pair.taker_fee_covers_payouts(),
+            ErrorCode::InvalidFeeRate
+        );
+
+        emit!(FeesConfiguredEvent {
+            trading_pair_id,
+            maker_fee_bps,
+            taker_fee_bps,
+            maker_rebate_bps,
+        });
+        Ok(())
+    }
+
+    /// Set the volume tiers fee discounts are selected from; thresholds and
+    /// discounts must not decrease from one tier to the next
+    pub fn configure_fee_tiers(
+        ctx: Context<ConfigureFees>,
+        trading_pair_id: u64,
+        fee_tiers: [FeeTier; FEE_TIERS],
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(
+            fee_tiers.iter().all(|tier| tier.discount_bps <= 10_000),
+            ErrorCode::InvalidFeeTiers
+        );
+        require!(
+            fee_tiers.windows(2).all(|pair| {
+                pair[0].min_volume <= pair[1].min_volume
+                    && pair[0].discount_bps <= pair[1].discount_bps
+            }),
+            ErrorCode::InvalidFeeTiers
+        );
+
+        trading_pair.fee_tiers = fee_tiers;
+
+        emit!(FeeTiersConfiguredEvent {
+            trading_pair_id,
+            fee_tiers,
+        });
+        Ok(())
+    }
+
+    /// Put a trading pair on a frequent batch auction schedule of
+    /// `interval_slots` per epoch, or back to continuous matching with zero.
+    /// The first auction runs at the next epoch boundary.
+    pub fn configure_auction(
+        ctx: Context<ConfigureFees>,
+        trading_pair_id: u64,
+        interval_slots: u64,
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        trading_pair.auction_interval_slots = interval_slots;
+        trading_pair.last_auction_epoch = trading_pair.auction_epoch(Clock::get()?.slot);
+
+        emit!(AuctionConfiguredEvent {
+            trading_pair_id,
+            interval_slots,
+            auction_epoch: trading_pair.last_auction_epoch,
+        });
+        Ok(())
+    }
+
+    /// Set the minimum slots between indicative price reveals
+    pub fn configure_indicative_price(
+        ctx: Context<ConfigureFees>,
+        trading_pair_id: u64,
+        interval_slots: u64,
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        trading_pair.indicative_interval_slots = interval_slots;
+        Ok(())
+    }
+
+    /// Schedule the phases of a trading session by slot, once or repeating
+    /// every period, or clear the schedule with all zeros and leave the phase
+    /// to `set_session_state`
+    pub fn configure_session(
+        ctx: Context<ConfigureFees>,
+        trading_pair_id: u64,
+        schedule: SessionSchedule,
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(
+            !schedule.is_set() || schedule.is_valid(),
+            ErrorCode::InvalidSessionSchedule
+        );
+        trading_pair.session_schedule = schedule;
+        trading_pair.advance_session(Clock::get()?.slot);
+
+        emit!(SessionConfiguredEvent {
+            trading_pair_id,
+            schedule,
+        });
+        Ok(())
+    }
+
+    /// Move a trading pair into a session phase by hand, such as halting it.
+    /// A scheduled session resumes its schedule at the next instruction
+    /// unless it is halted.
+    pub fn set_session_state(
+        ctx: Context<ConfigureFees>,
+        trading_pair_id: u64,
+        state: SessionState,
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        trading_pair.set_session_state(state, Clock::get()?.slot);
+        Ok(())
+    }
+
+    /// Apply a trading pair's session schedule; anyone may crank it
+    pub fn advance_session(ctx: Context<AdvanceSession>, trading_pair_id: u64) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        trading_pair.advance_session(Clock::get()?.slot);
+        Ok(())
+    }
+
+    /// Set the slots an unchanged book waits after a match that produced no
+    /// trades, the reward paid to whoever cranked a productive match and the
+    /// lamport bond a cranker posts with each match. The reward is at most
+    /// MAX_CRANK_REWARD_TOKENS whole quote tokens and the bond at most
+    /// MAX_MATCH_BOND; a bond without an interval still makes every empty
+    /// match cost its cranker.
+    pub fn configure_crank(
+        ctx: Context<ConfigureFees>,
+        trading_pair_id: u64,
+        match_interval_slots: u64,
+        crank_reward: u64,
+        match_bond: u64,
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        let max_crank_reward = 10u64
+            .checked_pow(trading_pair.quote_decimals as u32)
+            .and_then(|one_quote_token| one_quote_token.checked_mul(MAX_CRANK_REWARD_TOKENS))
+            .unwrap_or(u64::MAX);
+        require!(
+            crank_reward <= max_crank_reward && match_bond <= MAX_MATCH_BOND,
+            ErrorCode::InvalidCrankConfig
+        );
+
+        trading_pair.match_interval_slots = match_interval_slots;
+        trading_pair.crank_reward = crank_reward;
+        trading_pair.match_bond = match_bond;
+
+        emit!(CrankConfiguredEvent {
+            trading_pair_id,
+            match_interval_slots,
+            crank_reward,
+            match_bond,
+        });
+        Ok(())
+    }
+
+    /// Set the share of referred takers' fees owed to their referrers, in
+    /// basis points of the fee
+    pub fn configure_referrals(
+        ctx: Context<ConfigureFees>,
+        trading_pair_id: u64,
+        referral_share_bps: u16,
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        trading_pair.referral_share_bps = referral_share_bps;
+        require!(
+            trading_pair.taker_fee_covers_payouts(),
+            ErrorCode::InvalidFeeRate
+        );
+
+        emit!(ReferralsConfiguredEvent {
+            trading_pair_id,
+            referral_share_bps,
+        });
+        Ok(())
+    }
+
+    /// Create the account a partner front-end accrues referral shares in
+    pub fn register_referrer(ctx: Context<RegisterReferrer>, trading_pair_id: u64) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        let referrer = &mut ctx.accounts.referrer;
+        referrer.trading_pair = ctx.accounts.trading_pair.key();
+        referrer.owner = ctx.accounts.owner.key();
+        referrer.accrued = 0;
+        referrer.claimed = 0;
+        referrer.bump = ctx.bumps.referrer;
+        Ok(())
+    }
+
+    /// Pay a referrer's accrued shares out of the fee vault
+    pub fn claim_referral_fees(
+        ctx: Context<ClaimReferralFees>,
+        trading_pair_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        let amount = ctx.accounts.referrer.accrued;
+        require!(amount > 0, ErrorCode::InvalidQuantity);
+
+        let [low_mint, high_mint] = ctx.accounts.trading_pair.seed_mints();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"trading_pair",
+            low_mint.as_ref(),
+            high_mint.as_ref(),
+            &[ctx.accounts.trading_pair.bump],
+        ]];
+        transfer_checked_net(
+            &ctx.accounts.token_program,
+            ctx.accounts.fee_vault.to_account_info(),
+            &ctx.accounts.quote_mint,
+            ctx.accounts.destination.to_account_info(),
+            ctx.accounts.trading_pair.to_account_info(),
+            signer_seeds,
+            amount,
+        )?;
+
+        let referrer = &mut ctx.accounts.referrer;
+        referrer.accrued = 0;
+        referrer.claimed = referrer
+            .claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        trading_pair.referral_owed = trading_pair.referral_owed.saturating_sub(amount);
+
+        emit!(ReferralFeesClaimedEvent {
+            trading_pair_id,
+            referrer: referrer.owner,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Drop the signer's referrer on a pair so another can be set with their
+    /// next order. Their open orders carry the referral they were placed
+    /// with, so all of them must be closed and settled first.
+    pub fn clear_referrer(ctx: Context<ClearReferrer>, trading_pair_id: u64) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        let user_account = &mut ctx.accounts.user_account;
+        require!(user_account.open_orders == 0, ErrorCode::ReferredOrdersOpen);
+        user_account.referrer = Pubkey::default();
+        Ok(())
+    }
+
+    /// Pay collected fees out of the fee vault
+    pub fn withdraw_fees(
+        ctx: Context<WithdrawFees>,
+        trading_pair_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(amount > 0, ErrorCode::InvalidQuantity);
+        // Referral shares held for referrers are not the authority's to take
+        require!(
+            ctx.accounts.fee_vault.amount.saturating_sub(amount)
+                >= ctx.accounts.trading_pair.referral_owed,
+            ErrorCode::InsufficientBalance
+        );
+
+        let [low_mint, high_mint] = ctx.accounts.trading_pair.seed_mints();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"trading_pair",
+            low_mint.as_ref(),
+            high_mint.as_ref(),
+            &[ctx.accounts.trading_pair.bump],
+        ]];
+        transfer_checked_net(
+            &ctx.accounts.token_program,
+            ctx.accounts.fee_vault.to_account_info(),
+            &ctx.accounts.quote_mint,
+            ctx.accounts.destination.to_account_info(),
+            ctx.accounts.trading_pair.to_account_info(),
+            signer_seeds,
+            amount,
+        )?;
+
+        emit!(FeesWithdrawnEvent {
+            trading_pair_id,
+            destination: ctx.accounts.destination.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Set the x25519 key book-mutating computations encrypt audit records to;
+    /// an all-zero key disables auditing
+    pub fn set_auditor(
+        ctx: Context<SetAuditor>,
+        trading_pair_id: u64,
+        auditor_pubkey: [u8; 32],
+    ) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        // In-flight callbacks match their record against the current key
+        require!(!trading_pair.book_locked, ErrorCode::OrderBookLocked);
+
+        trading_pair.auditor_pubkey = auditor_pubkey;
+        emit!(AuditorUpdatedEvent {
+            trading_pair_id,
+            auditor_pubkey,
+        });
+        Ok(())
+    }
+
+    /// Set how long match prints stay encrypted on the trade tape
+    pub fn configure_tape(
+        ctx: Context<ConfigureTape>,
+        trading_pair_id: u64,
+        delay_seconds: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(delay_seconds >= 0, ErrorCode::InvalidTapeDelay);
+        ctx.accounts.trade_tape.delay_seconds = delay_seconds;
+        Ok(())
+    }
+
+    /// Decrypt a trade tape entry into public prints once its delay has passed.
+    /// Anyone may call this. Matches never wait on it: once the tape is full a
+    /// new match overwrites the oldest entry, and an entry dropped before it
+    /// was revealed is reported with `TapeEntryDroppedEvent`.
+    pub fn reveal_tape(
+        ctx: Context<RevealTape>,
+        computation_offset: u64,
+        trading_pair_id: u64,
+        sequence: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+
+        let trade_tape = &ctx.accounts.trade_tape;
+        let entry = trade_tape.entry(sequence);
+        require!(
+            entry.pending && entry.sequence == sequence,
+            ErrorCode::TapeEntryNotFound
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= entry.reveal_after,
+            ErrorCode::TapeEntryTooRecent
+        );
+
+        // TapeBatch: prices, quantities and timestamps of 5 trades, then trade_count
+        let mut args = vec![Argument::PlaintextU128(entry.nonce)];
+        for ciphertext in &entry.ciphertexts[..15] {
+            args.push(Argument::EncryptedU64(*ciphertext));
+        }
+        args.push(Argument::EncryptedU8(entry.ciphertexts[15]));
+        args.push(Argument::PlaintextU64(sequence));
+
+        let trading_pair_key = ctx.accounts.trading_pair.key();
+        ctx.accounts
+            .trading_pair
+            .claim_computation_offset(&trading_pair_key, computation_offset)?;
+        let trade_tape_key = ctx.accounts.trade_tape.key();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RevealTapeCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: trading_pair_key,
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: trade_tape_key,
+                    is_writable: true,
+                },
+            ])],
+        )?;
+
+        Ok(())
+    }
+
+    /// Callback handler for trade tape reveals
+    #[arcium_callback(encrypted_ix = "reveal_tape")]
+    pub fn reveal_tape_callback(
+        ctx: Context<RevealTapeCallback>,
+        output: ComputationOutputs<RevealTapeOutput>,
+    ) -> Result<()> {
+        let (sequence, batch) = match output {
+            ComputationOutputs::Success(RevealTapeOutput {
+                field_0:
+                    RevealTapeOutputStruct0 {
+                        field_0: sequence,
+                        field_1: batch,
+                    },
+            }) => (sequence, batch),
+            _ => return Err(ErrorCode::AbortedComputation.into()),
+        };
+
+        // A concurrent reveal of the same entry may have landed first
+        let entry = ctx.accounts.trade_tape.entry_mut(sequence);
+        require!(
+            entry.pending && entry.sequence == sequence,
+            ErrorCode::TapeEntryNotFound
+        );
+        entry.pending = false;
+
+        emit!(TapePrintEvent {
+            trading_pair_id: ctx.accounts.trading_pair.trading_pair_id,
+            sequence,
+            recorded_at: entry.recorded_at,
+            prices: batch.field_0,
+            quantities: batch.field_1,
+            timestamps: batch.field_2,
+            trade_count: batch.field_3,
+        });
+
+        Ok(())
+    }
+
+    /// Publish bucketed depth for a trading pair, at most once per configured interval
+    pub fn reveal_depth(
+        ctx: Context<RevealDepth>,
+        computation_offset: u64,
+        trading_pair_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(
+            ctx.accounts.trading_pair.depth_enabled,
+            ErrorCode::DepthDisabled
+        );
+
+        let slot = Clock::get()?.slot;
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            slot >= trading_pair
+                .last_depth_slot
+                .saturating_add(trading_pair.depth_interval_slots),
+            ErrorCode::DepthRateLimited
+        );
+        trading_pair.last_depth_slot = slot;
+
+        let trading_pair_key = trading_pair.key();
+        trading_pair.claim_computation_offset(&trading_pair_key, computation_offset)?;
+
+        let args = vec![
+            // Current order book
+            Argument::PlaintextU128(trading_pair.order_book_nonce),
+            OrderBook::argument(ctx.accounts.order_book.key()),
+            Argument::PlaintextU64(trading_pair.depth_band_width),
+            Argument::PlaintextU64(trading_pair.depth_lot_size),
+            Argument::PlaintextU64(trading_pair.depth_min_orders as u64),
+            Argument::PlaintextU64(trading_pair.book_version),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![RevealDepthCallback::callback_ix(&[CallbackAccount {
+                pubkey: trading_pair_key,
+                is_writable: true,
+            }])],
+        )?;
+
+        Ok(())
+    }
+
+    /// Callback handler for depth disclosure
+    #[arcium_callback(encrypted_ix = "reveal_depth")]
+    pub fn reveal_depth_callback(
+        ctx: Context<RevealDepthCallback>,
+        output: ComputationOutputs<RevealDepthOutput>,
+    ) -> Result<()> {
+        let (computed_version, snapshot) = match output {
+            ComputationOutputs::Success(RevealDepthOutput {
+                field_0:
+                    RevealDepthOutputStruct0 {
+                        field_0: computed_version,
+                        field_1: snapshot,
+                    },
+            }) => (computed_version, snapshot),
+            _ => {
+                // Nothing was published, so the rate limit is lifted for a retry
+                ctx.accounts.trading_pair.last_depth_slot = 0;
+                return Ok(());
+            }
+        };
+
+        // A rewrite since queueing means the book was read with a stale nonce
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        if computed_version != trading_pair.book_version {
+            trading_pair.last_depth_slot = 0;
+            return Ok(());
+        }
+
+        emit!(DepthSnapshotEvent {
+            trading_pair_id: trading_pair.trading_pair_id,
+            book_version: computed_version,
+            band_width: trading_pair.depth_band_width,
+            lot_size: trading_pair.depth_lot_size,
+            min_orders: trading_pair.depth_min_orders,
+            mid_price: snapshot.field_0,
+            bid_depth: snapshot.field_1,
+            ask_depth: snapshot.field_2,
+        });
+
+        Ok(())
+    }
+
+    /// Publish the uniform price the book would uncross at if a match ran
+    /// now, at most once per configured interval
+    pub fn indicative_price(
+        ctx: Context<IndicativePrice>,
+        computation_offset: u64,
+        trading_pair_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(
+            ctx.accounts.trading_pair.is_active,
+            ErrorCode::TradingPairInactive
+        );
+        let slot = Clock::get()?.slot;
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        trading_pair.enter_session(slot, SessionAction::IndicativePrice)?;
+        require!(
+            slot >= trading_pair
+                .last_indicative_slot
+                .saturating_add(trading_pair.indicative_interval_slots),
+            ErrorCode::IndicativePriceRateLimited
+        );
+        trading_pair.last_indicative_slot = slot;
+
+        let trading_pair_key = trading_pair.key();
+        trading_pair.claim_computation_offset(&trading_pair_key, computation_offset)?;
+
+        let args = vec![
+            // Current order book
+            Argument::PlaintextU128(trading_pair.order_book_nonce),
+            OrderBook::argument(ctx.accounts.order_book.key()),
+            // The orders a match queued now would uncross
+            Argument::PlaintextU64(trading_pair.pending_cutoff(slot)),
+            Argument::PlaintextU64(trading_pair.book_version),
+        ];
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![IndicativePriceCallback::callback_ix(&[CallbackAccount {
+                pubkey: trading_pair_key,
+                is_writable: true,
+            }])],
+        )?;
+
+        Ok(())
+    }
+
+    /// Callback handler for indicative prices
+    #[arcium_callback(encrypted_ix = "indicative_price")]
+    pub fn indicative_price_callback(
+        ctx: Context<IndicativePriceCallback>,
+        output: ComputationOutputs<IndicativePriceOutput>,
+    ) -> Result<()> {
+        let (computed_version, indicative) = match output {
+            ComputationOutputs::Success(IndicativePriceOutput {
+                field_0:
+                    IndicativePriceOutputStruct0 {
+                        field_0: computed_version,
+                        field_1: indicative,
+                    },
+            }) => (computed_version, indicative),
+            _ => {
+                // Nothing was published, so the rate limit is lifted for a retry
+                ctx.accounts.trading_pair.last_indicative_slot = 0;
+                return Ok(());
+            }
+        };
+
+        // A rewrite since queueing means the book was read with a stale nonce
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        if computed_version != trading_pair.book_version {
+            trading_pair.last_indicative_slot = 0;
+            return Ok(());
+        }
+
+        let imbalance = match indicative.field_2 {
+            1 => ImbalanceSide::Buy,
+            2 => ImbalanceSide::Sell,
+            _ => ImbalanceSide::None,
+        };
+        emit!(IndicativePriceEvent {
+            trading_pair_id: trading_pair.trading_pair_id,
+            book_version: computed_version,
+            price: indicative.field_0,
+            crossed: indicative.field_1,
+            imbalance,
+        });
+
+        Ok(())
+    }
+
+    /// Register or rotate the x25519 key the MXE encrypts a trader's results to
+    pub fn register_encryption_key(
+        ctx: Context<RegisterEncryptionKey>,
+        pubkey: [u8; 32],
+    ) -> Result<()> {
+        require!(pubkey != [0u8; 32], ErrorCode::InvalidEncryptionKey);
+
+        let encryption_key = &mut ctx.accounts.encryption_key;
+        let is_rotation = encryption_key.trader != Pubkey::default();
+        encryption_key.trader = ctx.accounts.trader.key();
+        encryption_key.bump = ctx.bumps.encryption_key;
+        encryption_key.pubkey = pubkey;
+        if is_rotation {
+            encryption_key.key_version = encryption_key
+                .key_version
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        emit!(EncryptionKeyRegisteredEvent {
+            trader: encryption_key.trader,
+            pubkey,
+            key_version: encryption_key.key_version,
+        });
+
+        Ok(())
+    }
+
+    /// Create the token vaults that hold escrowed funds for a trading pair
+    pub fn initialize_vaults(ctx: Context<InitializeVaults>, trading_pair_id: u64) -> Result<()> {
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        require!(
+            trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        trading_pair.base_vault = ctx.accounts.base_vault.key();
+        trading_pair.quote_vault = ctx.accounts.quote_vault.key();
+        trading_pair.fee_vault = ctx.accounts.fee_vault.key();
+        Ok(())
+    }
+
+    /// Clear a failed or timed-out computation, releasing the book lock and
+    /// refunding any escrow held for a failed order submission
+    pub fn clear_pending_computation(
+        ctx: Context<ClearPendingComputation>,
+        trading_pair_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+
+        let current_slot = Clock::get()?.slot;
+        let pending_computation = &ctx.accounts.pending_computation;
+        require!(
+            pending_computation.is_recoverable(current_slot),
+            ErrorCode::ComputationNotRecoverable
+        );
+
+        let escrow_amount = pending_computation.escrow_amount;
+        if escrow_amount > 0 {
+            let (
+                Some(escrow_vault),
+                Some(escrow_refund_account),
+                Some(escrow_mint),
+                Some(user_account),
+                Some(token_program),
+            ) = (
+                &ctx.accounts.escrow_vault,
+                &ctx.accounts.escrow_refund_account,
+                &ctx.accounts.escrow_mint,
+                &mut ctx.accounts.user_account,
+                &ctx.accounts.token_program,
+            )
+            else {
+                return Err(ErrorCode::InvalidEscrowAccount.into());
+            };
+            require!(
+                escrow_vault.key() == pending_computation.escrow_vault,
+                ErrorCode::InvalidEscrowAccount
+            );
+            require!(
+                escrow_refund_account.key() == pending_computation.escrow_refund_account,
+                ErrorCode::InvalidEscrowAccount
+            );
+            require!(
+                escrow_mint.key() == escrow_vault.mint,
+                ErrorCode::InvalidEscrowAccount
+            );
+
+            let is_base = ctx.accounts.trading_pair.vault_is_base(&escrow_vault.key())?;
+            user_account.unlock(is_base, escrow_amount)?;
+            user_account.withdraw(is_base, escrow_amount)?;
+
+            let [low_mint, high_mint] = ctx.accounts.trading_pair.seed_mints();
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"trading_pair",
+                low_mint.as_ref(),
+                high_mint.as_ref(),
+                &[ctx.accounts.trading_pair.bump],
+            ]];
+            transfer_checked_net(
+                token_program,
+                escrow_vault.to_account_info(),
+                escrow_mint,
+                escrow_refund_account.to_account_info(),
+                ctx.accounts.trading_pair.to_account_info(),
+                signer_seeds,
+                escrow_amount,
+            )?;
+        }
+
+        // A match that timed out still holds its MatchFills account; it is
+        // closed so the next match starts afresh, and the cranker gets the
+        // rent and bond back as when the match fails
+        if pending_computation.kind == ComputationKind::MatchOrders
+            && pending_computation.status == ComputationStatus::Queued
+        {
+            let (Some(match_fills), Some(cranker)) =
+                (&ctx.accounts.match_fills, &ctx.accounts.cranker)
+            else {
+                return Err(ErrorCode::InvalidMatchFills.into());
+            };
+            require_keys_eq!(
+                cranker.key(),
+                match_fills.cranker,
+                ErrorCode::InvalidMatchFills
+            );
+            match_fills.close(cranker.to_account_info())?;
+        }
+
+        let computation_offset = pending_computation.computation_offset;
+        let kind = pending_computation.kind;
+
+        // Bump the version so a late callback from the abandoned computation is rejected as stale
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        trading_pair.book_version = trading_pair
+            .book_version
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        trading_pair.book_locked = false;
+        // A pair whose order book never initialized cannot trade
+        if kind == ComputationKind::InitOrderBook {
+            trading_pair.is_active = false;
+        }
+        // An abandoned match did not happen, so the auction epoch or phase it
+        // opened may run it again
+        if kind == ComputationKind::MatchOrders {
+            trading_pair.last_auction_epoch =
+                ctx.accounts.pending_computation.previous_auction_epoch;
+            trading_pair.uncrossed_session_slot = 0;
+        }
+
+        ctx.accounts.pending_computation.complete();
+
+        emit!(PendingComputationClearedEvent {
+            trading_pair_id,
+            computation_offset,
+            kind,
+            escrow_refunded: escrow_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Re-queue a failed or timed-out order submission with its original encrypted payload
+    pub fn requeue_order(
+        ctx: Context<RequeueOrder>,
+        computation_offset: u64,
+        trading_pair_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+
+        let clock = Clock::get()?;
+        ctx.accounts
+            .trading_pair
+            .enter_session(clock.slot, SessionAction::SubmitOrder)?;
+        let receipt_nonce = ctx.accounts.encryption_key.next_output_nonce()?;
+        let (audit, auditor_pubkey, auditor_nonce) = ctx
+            .accounts
+            .trading_pair
+            .audit_receiver(&ctx.accounts.mxe_account)?;
+        let pending_computation = &mut ctx.accounts.pending_computation;
+        require!(
+            pending_computation.is_recoverable(clock.slot),
+            ErrorCode::ComputationNotRecoverable
+        );
+        require!(
+            pending_computation.kind == ComputationKind::SubmitOrder,
+            ErrorCode::CannotRequeue
+        );
+        require!(
+            pending_computation.submitter == ctx.accounts.payer.key(),
+            ErrorCode::UnauthorizedSubmitter
+        );
+
+        // Keep the lock held but move to a new version so the abandoned computation goes stale
+        let trading_pair_key = ctx.accounts.trading_pair.key();
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        trading_pair.claim_computation_offset(&trading_pair_key, computation_offset)?;
+        trading_pair.book_version = trading_pair
+            .book_version
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let book_version = trading_pair.book_version;
+
+        pending_computation.status = ComputationStatus::Queued;
+        pending_computation.computation_offset = computation_offset;
+        pending_computation.queued_slot = clock.slot;
+
+        let [encrypted_price, encrypted_quantity, encrypted_is_buy] =
+            pending_computation.ciphertexts;
+        let escrow_is_base = pending_computation.escrow_amount > 0
+            && ctx
+                .accounts
+                .trading_pair
+                .vault_is_base(&pending_computation.escrow_vault)?;
+        let args = vec![
+            Argument::ArcisPubkey(pending_computation.client_pubkey),
+            Argument::PlaintextU128(pending_computation.client_nonce),
+            Argument::EncryptedU64(encrypted_price),
+            Argument::EncryptedU64(encrypted_quantity),
+            Argument::EncryptedBool(encrypted_is_buy),
+            Argument::PlaintextU128(trader_id(&pending_computation.submitter)),
+            Argument::PlaintextU64(pending_computation.timestamp),
+            Argument::PlaintextU64(pending_computation.slot),
+            Argument::PlaintextU64(pending_computation.escrow_amount),
+            Argument::PlaintextBool(escrow_is_base),
+            Argument::PlaintextBool(pending_computation.has_referrer),
+            // Market parameters
+            Argument::PlaintextU64(ctx.accounts.trading_pair.tick_size),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.lot_size),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.min_order_size),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.max_order_price),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.max_order_quantity),
+            Argument::PlaintextU64(ctx.accounts.trading_pair.price_scale),
+            Argument::PlaintextU64(MAX_ORDER_NOTIONAL),
+            // Current order book
+            Argument::PlaintextU128(ctx.accounts.trading_pair.order_book_nonce),
+            OrderBook::argument(ctx.accounts.order_book.key()),
+            Argument::PlaintextU64(book_version),
+            // Receipt recipient: the trader's registered key
+            Argument::ArcisPubkey(ctx.accounts.encryption_key.pubkey),
+            Argument::PlaintextU128(receipt_nonce),
+            // Audit record recipient
+            Argument::PlaintextBool(audit),
+            Argument::ArcisPubkey(auditor_pubkey),
+            Argument::PlaintextU128(auditor_nonce),
+        ];
+        let order_book_key = ctx.accounts.order_book.key();
+        let pending_computation_key = ctx.accounts.pending_computation.key();
+        let computation_account_key = ctx.accounts.computation_account.key();
+        let user_account_key = ctx.accounts.user_account.key();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![SubmitOrderCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: trading_pair_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: order_book_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: pending_computation_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: user_account_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: computation_account_key,
+                    is_writable: false,
+                },
+            ])],
+        )?;
+
+        emit!(OrderRequeuedEvent {
+            trading_pair_id,
+            computation_offset,
+            book_version,
+        });
+
+        Ok(())
+    }
+
+    /// Deposit tokens into a pair vault, credited to the trader's free balance
+    pub fn deposit(ctx: Context<Deposit>, trading_pair_id: u64, amount: u64) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(amount > 0, ErrorCode::InvalidQuantity);
+
+        let is_base = ctx.accounts.trading_pair.vault_is_base(&ctx.accounts.vault.key())?;
+
+        // Only what reaches the vault after any transfer fee is credited
+        let received = transfer_checked_net(
+            &ctx.accounts.token_program,
+            ctx.accounts.source.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.owner.to_account_info(),
+            &[],
+            amount,
+        )?;
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.owner = ctx.accounts.owner.key();
+        user_account.trading_pair = ctx.accounts.trading_pair.key();
+        user_account.bump = ctx.bumps.user_account;
+        user_account.deposit(is_base, received)?;
+
+        emit!(EscrowDepositedEvent {
+            trading_pair_id,
+            owner: user_account.owner,
+            is_base,
+            amount: received,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw tokens from the trader's free (unlocked) balance
+    pub fn withdraw(ctx: Context<Withdraw>, trading_pair_id: u64, amount: u64) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(amount > 0, ErrorCode::InvalidQuantity);
+
+        let is_base = ctx.accounts.trading_pair.vault_is_base(&ctx.accounts.vault.key())?;
+        ctx.accounts.user_account.withdraw(is_base, amount)?;
+
+        let [low_mint, high_mint] = ctx.accounts.trading_pair.seed_mints();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"trading_pair",
+            low_mint.as_ref(),
+            high_mint.as_ref(),
+            &[ctx.accounts.trading_pair.bump],
+        ]];
+        // Any transfer fee is withheld from what the destination receives
+        transfer_checked_net(
+            &ctx.accounts.token_program,
+            ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.destination.to_account_info(),
+            ctx.accounts.trading_pair.to_account_info(),
+            signer_seeds,
+            amount,
+        )?;
+
+        emit!(EscrowWithdrawnEvent {
+            trading_pair_id,
+            owner: ctx.accounts.owner.key(),
+            is_base,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Deposit lamports into the vault of a pair's native SOL side, base or
+    /// quote, wrapped in place and credited to the trader's free balance
+    pub fn deposit_sol(
+        ctx: Context<DepositSol>,
+        trading_pair_id: u64,
+        lamports: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(lamports > 0, ErrorCode::InvalidQuantity);
+        let vault = ctx.accounts.vault.key();
+        let is_base = ctx.accounts.trading_pair.vault_is_base(&vault)?;
+
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.owner.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, lamports)?;
+
+        // Bring the vault's token balance in line with its new lamports
+        let cpi_accounts = SyncNative {
+            account: ctx.accounts.vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token_interface::sync_native(cpi_ctx)?;
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.owner = ctx.accounts.owner.key();
+        user_account.trading_pair = ctx.accounts.trading_pair.key();
+        user_account.bump = ctx.bumps.user_account;
+        user_account.deposit(is_base, lamports)?;
+
+        emit!(EscrowDepositedEvent {
+            trading_pair_id,
+            owner: user_account.owner,
+            is_base,
+            amount: lamports,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw a trader's free balance on a pair's native SOL side as
+    /// lamports: the wrapped SOL moves to a temporary token account that is
+    /// closed to the trader
+    pub fn withdraw_sol(
+        ctx: Context<WithdrawSol>,
+        trading_pair_id: u64,
+        lamports: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(lamports > 0, ErrorCode::InvalidQuantity);
+        let vault = ctx.accounts.vault.key();
+        let is_base = ctx.accounts.trading_pair.vault_is_base(&vault)?;
+        ctx.accounts.user_account.withdraw(is_base, lamports)?;
+
+        let [low_mint, high_mint] = ctx.accounts.trading_pair.seed_mints();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"trading_pair",
+            low_mint.as_ref(),
+            high_mint.as_ref(),
+            &[ctx.accounts.trading_pair.bump],
+        ]];
+        transfer_checked_net(
+            &ctx.accounts.token_program,
+            ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.unwrap_account.to_account_info(),
+            ctx.accounts.trading_pair.to_account_info(),
+            signer_seeds,
+            lamports,
+        )?;
+
+        // Closing pays out the wrapped lamports along with the rent the
+        // trader put up for the temporary account
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.unwrap_account.to_account_info(),
+            destination: ctx.accounts.owner.to_account_info(),
+            authority: ctx.accounts.trading_pair.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        token_interface::close_account(cpi_ctx)?;
+
+        emit!(EscrowWithdrawnEvent {
+            trading_pair_id,
+            owner: ctx.accounts.owner.key(),
+            is_base,
+            amount: lamports,
+        });
+
+        Ok(())
+    }
+
+    /// Close a trader's account once it has no open orders and no escrowed funds
+    pub fn close_user_account(ctx: Context<CloseUserAccount>, trading_pair_id: u64) -> Result<()> {
+        require!(
+            ctx.accounts.trading_pair.trading_pair_id == trading_pair_id,
+            ErrorCode::InvalidTradingPairId
+        );
+        require!(
+            ctx.accounts.user_account.is_empty(),
+            ErrorCode::UserAccountNotEmpty
+        );
+        Ok(())
+    }
+
+    /// Execute token transfers for matched trades
+    /// Called after MPC reveals matched trades. Nothing here can tell which
+    /// side was the aggressor, so no protocol fee is charged; fees are taken
+    /// where the MPC decides the taker, through match_orders and settle_fills.
+    pub fn execute_trade(
+        ctx: Context<ExecuteTrade>,
+        buyer_id: u128,
+        seller_id: u128,
+        trade_price: u64,
+        trade_quantity: u64,
+    ) -> Result<()> {
+        // Validate trade parameters
+        let slot = Clock::get()?.slot;
+        let trading_pair = &mut ctx.accounts.trading_pair;
+        trading_pair.enter_session(slot, SessionAction::ExecuteTrade)?;
+        require!(
+            trade_price > 0 && trade_price % trading_pair.tick_size == 0,
+            ErrorCode::InvalidPrice
+        );
+        require!(
+            trade_quantity >= trading_pair.min_order_size
+                && trade_quantity % trading_pair.lot_size == 0,
+            ErrorCode::InvalidQuantity
+        );
+
+        // Deserialize and validate token accounts
+        let buyer_quote = TokenAccount::try_deserialize(&mut &ctx.accounts.buyer_quote_account.try_borrow_data()?[..])?;
+        let seller_base = TokenAccount::try_deserialize(&mut &ctx.accounts.seller_base_account.try_borrow_data()?[..])?;
+        let buyer_base = TokenAccount::try_deserialize(&mut &ctx.accounts.buyer_base_account.try_borrow_data()?[..])?;
+        let seller_quote = TokenAccount::try_deserialize(&mut &ctx.accounts.seller_quote_account.try_borrow_data()?[..])?;
+
+        // Validate token accounts belong to the correct traders
+        require!(buyer_quote.owner == ctx.accounts.buyer.key(), ErrorCode::InvalidTokenAccount);
+        require!(seller_base.owner == ctx.accounts.seller.key(), ErrorCode::InvalidTokenAccount);
+        require!(buyer_base.owner == ctx.accounts.buyer.key(), ErrorCode::InvalidTokenAccount);
+        require!(seller_quote.owner == ctx.accounts.seller.key(), ErrorCode::InvalidTokenAccount);
+        require!(
+            buyer_base.mint == trading_pair.base_mint
+                && seller_base.mint == trading_pair.base_mint
+                && buyer_quote.mint == trading_pair.quote_mint
+                && seller_quote.mint == trading_pair.quote_mint,
+            ErrorCode::InvalidTokenAccount
+        );
+
+        // The buyer pays the notional rounded up and the seller receives it
+        // rounded down; the remainder goes to the quote vault as dust
+        let quote_amount = trading_pair.quote_amount(trade_price, trade_quantity, Rounding::Up)?;
+        let proceeds = trading_pair.quote_amount(trade_price, trade_quantity, Rounding::Down)?;
+        let dust = quote_amount - proceeds;
+
+        // Verify sufficient balances before executing transfers
+        require!(
+            buyer_quote.amount >= quote_amount,
+            ErrorCode::InsufficientBalance
+        );
+        require!(
+            seller_base.amount >= trade_quantity,
+            ErrorCode::InsufficientBalance
+        );
+
+        // Transfer quote tokens from buyer to seller
+        let quote_received = transfer_checked_net(
+            &ctx.accounts.quote_token_program,
+            ctx.accounts.buyer_quote_account.to_account_info(),
+            &ctx.accounts.quote_mint,
+            ctx.accounts.seller_quote_account.to_account_info(),
+            ctx.accounts.buyer.to_account_info(),
+            &[],
+            proceeds,
+        )?;
+
+        if dust > 0 {
+            let dust_received = transfer_checked_net(
+                &ctx.accounts.quote_token_program,
+                ctx.accounts.buyer_quote_account.to_account_info(),
+                &ctx.accounts.quote_mint,
+                ctx.accounts.quote_vault.to_account_info(),
+                ctx.accounts.buyer.to_account_info(),
+                &[],
+                dust,
+            )?;
+            trading_pair.accrue_dust(dust_received)?;
+        }
+
+        // Transfer base tokens from seller to buyer
+        let base_received = transfer_checked_net(
+            &ctx.accounts.base_token_program,
+            ctx.accounts.seller_base_account.to_account_info(),
+            &ctx.accounts.base_mint,
+            ctx.accounts.buyer_base_account.to_account_info(),
+            ctx.accounts.seller.to_account_info(),
+            &[],
+            trade_quantity,
+        )?;
+
+        emit!(TradeExecutedEvent {
+            buyer_id,
+            seller_id,
+            price: trade_price,
+            quantity: trade_quantity,
+            quote_amount,
+            dust,
+            base_received,
+            quote_received,
+            base_decimals: trading_pair.base_decimals,
+            quote_decimals: trading_pair.quote_decimals,
+            timestamp: Clock::get()?.unix_timestamp as u64,
+        });
+
+        Ok(())
+    }
+}
+
+/// Trading pair account storing encrypted order book state
+#[account]
+#[derive(InitSpace)]
+pub struct TradingPair {
+    /// Unique identifier for this trading pair
+    pub trading_pair_id: u64,
+    /// Base token mint (e.g., SOL)
+    pub base_mint: Pubkey,
+    /// Quote token mint (e.g., USDC)
+    pub quote_mint: Pubkey,
+    /// Nonce of the order book held in the pair's OrderBook account
+    pub order_book_nonce: u128,
+    /// Whether trading is active
+    pub is_active: bool,
+    /// Total orders submitted
+    pub total_orders: u64,
+    /// PDA bump
+    pub bump: u8,
+    /// Order book version, bumped by every accepted book-mutating callback
+    pub book_version: u64,
+    /// Whether a book-mutating computation is currently in flight
+    pub book_locked: bool,
+    /// Vault holding escrowed base tokens
+    pub base_vault: Pubkey,
+    /// Vault holding escrowed quote tokens
+    pub quote_vault: Pubkey,
+    /// Number of computations queued so far, used to derive the next computation offset
+    pub computation_count: u64,
+    /// What a match makes public and whether fills settle on-chain
+    pub disclosure_policy: DisclosurePolicy,
+    /// Wallet allowed to change the pair's configuration
+    pub authority: Pubkey,
+    /// Whether bucketed depth may be revealed
+    pub depth_enabled: bool,
+    /// Width of each depth band in price units
+    pub depth_band_width: u64,
+    /// Lot size band totals are rounded down to
+    pub depth_lot_size: u64,
+    /// Fewest orders a band must hold for its total to be shown
+    pub depth_min_orders: u8,
+    /// Minimum slots between depth reveals
+    pub depth_interval_slots: u64,
+    /// Slot of the last depth reveal
+    pub last_depth_slot: u64,
+    /// x25519 key audit records are encrypted to, all zeros when auditing is off
+    pub auditor_pubkey: [u8; 32],
+    /// Next nonce for audit outputs, whether encrypted to the auditor or the MXE
+    pub auditor_nonce: u128,
+    /// Prices must be a multiple of this, in quote atoms
+    pub tick_size: u64,
+    /// Quantities must be a multiple of this, in base atoms
+    pub lot_size: u64,
+    /// Smallest order quantity, in base atoms
+    pub min_order_size: u64,
+    /// Highest price an order may carry, in quote atoms
+    pub max_order_price: u64,
+    /// Highest quantity an order may carry, in base atoms
+    pub max_order_quantity: u64,
+    /// Number of base atoms a price is quoted for
+    pub price_scale: u64,
+    /// Decimals of the base mint
+    pub base_decimals: u8,
+    /// Decimals of the quote mint
+    pub quote_decimals: u8,
+    /// Quote atoms left in the quote vault by rounding buyer payments up and
+    /// seller proceeds down; owned by no user account
+    pub quote_dust: u64,
+    /// Token program owning the base mint, SPL Token or Token-2022
+    pub base_token_program: Pubkey,
+    /// Token program owning the quote mint, SPL Token or Token-2022
+    pub quote_token_program: Pubkey,
+    /// Fee charged to the resting side of a fill, in basis points of notional
+    pub maker_fee_bps: u16,
+    /// Fee charged to the side that crossed the spread, in basis points of notional
+    pub taker_fee_bps: u16,
+    /// Part of the taker fee paid to the resting side, in basis points of notional
+    pub maker_rebate_bps: u16,
+    /// Part of a referred taker's fee owed to their referrer, in basis points of the fee
+    pub referral_share_bps: u16,
+    /// Referral shares held in the fee vault until their referrers claim them
+    pub referral_owed: u64,
+    /// Slots an unchanged book waits after a match that produced no trades
+    pub match_interval_slots: u64,
+    /// Slot the last match that produced no trades landed in
+    pub last_empty_match_slot: u64,
+    /// Book version that match left, u64::MAX before any
+    pub empty_match_version: u64,
+    /// Quote atoms paid to the account that cranked a productive match
+    pub crank_reward: u64,
+    /// Lamports a cranker posts with each match, forfeited to the authority
+    /// when the match produces no trades
+    pub match_bond: u64,
+    /// Slots per auction epoch, zero for continuous matching
+    pub auction_interval_slots: u64,
+    /// Last auction epoch orders were matched in
+    pub last_auction_epoch: u64,
+    /// Trading session phase, deciding which actions are permitted
+    pub session_state: SessionState,
+    /// Slot the current session phase began in
+    pub session_started_slot: u64,
+    /// Slots the session phases begin in, all zero when unscheduled
+    pub session_schedule: SessionSchedule,
+    /// Slot the auction phase whose uncross was last queued began in; an
+    /// auction phase uncrosses once
+    pub uncrossed_session_slot: u64,
+    /// Quote token account fees are collected into
+    pub fee_vault: Pubkey,
+    /// Quote atoms collected into the fee vault over the pair's lifetime
+    pub fees_collected: u64,
+    /// Fee discounts by private cumulative volume, applied by the settle_fills circuit
+    pub fee_tiers: [FeeTier; FEE_TIERS],
+    /// Matches recorded for settlement, numbering their MatchFills accounts
+    pub match_count: u64,
+    /// Minimum slots between indicative price reveals
+    pub indicative_interval_slots: u64,
+    /// Slot of the last indicative price reveal
+    pub last_indicative_slot: u64,
+    /// Quote atoms held in the quote vault against tier discounts traders
+    /// have not claimed yet
+    pub fee_credit_reserved: u64,
+}
+
+/// Phase of a trading pair's session
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum SessionState {
+    /// Orders are collected for the opening auction without matching
+    PreOpen,
+    /// Orders collected before the phase began are uncrossed
+    OpeningAuction,
+    /// Orders match as they cross
+    Continuous,
+    /// Orders collected before the phase began are uncrossed for the close
+    ClosingAuction,
+    /// The session is over; resting orders can only be cancelled
+    Closed,
+    /// Trading is suspended by the authority until it picks another phase
+    Halted,
+}
+
+/// Instructions gated by the session phase
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SessionAction {
+    SubmitOrder,
+    CancelOrder,
+    MatchOrders,
+    ExecuteTrade,
+    IndicativePrice,
+}
+
+impl SessionState {
+    /// Whether `action` is permitted in this phase
+    pub fn permits(self, action: SessionAction) -> bool {
+        match self {
+            SessionState::PreOpen => matches!(
+                action,
+                SessionAction::SubmitOrder
+                    | SessionAction::CancelOrder
+                    | SessionAction::IndicativePrice
+            ),
+            SessionState::OpeningAuction | SessionState::ClosingAuction => {
+                action != SessionAction::ExecuteTrade
+            }
+            SessionState::Continuous => true,
+            SessionState::Closed | SessionState::Halted => action == SessionAction::CancelOrder,
+        }
+    }
+
+    /// Whether matching in this phase is a call auction
+    pub fn is_auction(self) -> bool {
+        matches!(
+            self,
+            SessionState::OpeningAuction | SessionState::ClosingAuction
+        )
+    }
+}
+
+/// Slots a trading session moves into each phase in. The session is closed
+/// before `pre_open_slot`, or pre-open from the start when it is zero, and
+/// with a non-zero `period_slots` it repeats every period from
+/// `pre_open_slot`.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SessionSchedule {
+    pub pre_open_slot: u64,
+    pub opening_auction_slot: u64,
+    pub continuous_slot: u64,
+    pub closing_auction_slot: u64,
+    pub closed_slot: u64,
+    /// Slots from one session's pre-open to the next, zero for a single
+    /// session
+    pub period_slots: u64,
+}
+
+impl SessionSchedule {
+    /// Whether the schedule drives the session
+    pub fn is_set(&self) -> bool {
+        *self != SessionSchedule::default()
+    }
+
+    /// Whether every phase is scheduled, in order, and a recurring session
+    /// ends before the next one opens
+    pub fn is_valid(&self) -> bool {
+        self.opening_auction_slot > 0
+            && self.pre_open_slot <= self.opening_auction_slot
+            && self.opening_auction_slot <= self.continuous_slot
+            && self.continuous_slot <= self.closing_auction_slot
+            && self.closing_auction_slot <= self.closed_slot
+            && (self.period_slots == 0
+                || (self.pre_open_slot > 0
+                    && self.closed_slot - self.pre_open_slot <= self.period_slots))
+    }
+
+    /// Scheduled phase at `slot` and the slot it began in
+    pub fn state_at(&self, slot: u64) -> (SessionState, u64) {
+        // A recurring session is read off the first one, shifted by the
+        // whole periods since it opened
+        let shift = if self.period_slots > 0 && slot >= self.pre_open_slot {
+            (slot - self.pre_open_slot) / self.period_slots * self.period_slots
+        } else {
+            0
+        };
+        let (state, started_slot) = match slot - shift {
+            slot if slot >= self.closed_slot => (SessionState::Closed, self.closed_slot),
+            slot if slot >= self.closing_auction_slot => {
+                (SessionState::ClosingAuction, self.closing_auction_slot)
+            }
+            slot if slot >= self.continuous_slot => {
+                (SessionState::Continuous, self.continuous_slot)
+            }
+            slot if slot >= self.opening_auction_slot => {
+                (SessionState::OpeningAuction, self.opening_auction_slot)
+            }
+            slot if slot >= self.pre_open_slot => (SessionState::PreOpen, self.pre_open_slot),
+            _ => return (SessionState::Closed, 0),
+        };
+        (state, started_slot + shift)
+    }
+}
+
+/// Fee discount for traders whose cumulative quote volume reaches `min_volume`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq)]
+pub struct FeeTier {
+    /// Cumulative quote volume the tier starts at
+    pub min_volume: u64,
+    /// Discount off the maker or taker fee, in basis points of the fee
+    pub discount_bps: u16,
+}
+
+impl FeeTier {
+    /// Tier no trader reaches
+    pub const DISABLED: FeeTier = FeeTier {
+        min_volume: u64::MAX,
+        discount_bps: 0,
+    };
+}
+
+/// Market parameters a trading pair is created with
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct MarketParams {
+    pub tick_size: u64,
+    pub lot_size: u64,
+    pub min_order_size: u64,
+    pub price_scale: u64,
+    pub max_order_price: u64,
+    pub max_order_quantity: u64,
+}
+
+impl MarketParams {
+    /// Check the parameters are usable with mints of `base_decimals` and
+    /// `quote_decimals`: a whole token of either mint fits in a u64, a tick
+    /// is at most one whole quote token, the price scale is a power of ten no
+    /// larger than one whole base token, and the largest order's notional is
+    /// at most MAX_ORDER_NOTIONAL. Notionals
+    /// need not be whole quote atoms; settlement rounds them in favour of the
+    /// vault and keeps the remainder as dust.
+    pub fn validate(&self, base_decimals: u8, quote_decimals: u8) -> Result<()> {
+        require!(
+            self.tick_size > 0 && self.lot_size > 0,
+            ErrorCode::InvalidMarketParams
+        );
+        require!(
+            self.min_order_size >= self.lot_size && self.min_order_size % self.lot_size == 0,
+            ErrorCode::InvalidMarketParams
+        );
+        require!(
+            self.max_order_price >= self.tick_size
+                && self.max_order_quantity >= self.min_order_size,
+            ErrorCode::InvalidMarketParams
+        );
+        let one_quote_token = 10u64
+            .checked_pow(quote_decimals as u32)
+            .ok_or(ErrorCode::InvalidMarketParams)?;
+        require!(
+            self.tick_size <= one_quote_token,
+            ErrorCode::InvalidMarketParams
+        );
+
+        let one_base_token = 10u64
+            .checked_pow(base_decimals as u32)
+            .ok_or(ErrorCode::InvalidMarketParams)?;
+        let mut scale = 1u64;
+        while scale < self.price_scale && scale < one_base_token {
+            scale *= 10;
+        }
+        require!(scale == self.price_scale, ErrorCode::InvalidMarketParams);
+
+        let max_notional = self.max_order_price as u128 * self.max_order_quantity as u128
+            / self.price_scale as u128;
+        require!(
+            max_notional <= MAX_ORDER_NOTIONAL as u128,
+            ErrorCode::InvalidMarketParams
+        );
+        Ok(())
+    }
+}
+
+/// Per-market choice of what order matching reveals
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum DisclosurePolicy {
+    /// Nothing leaves the MXE except encrypted fill reports; fills are not
+    /// settled on-chain and no protocol fee is charged on them
+    Private,
+    /// Trade prices and sizes are published and fills settle between user
+    /// accounts in the pair vaults; counterparties stay encrypted
+    PriceAndSize,
+}
+
+/// Direction a quote amount is rounded when the notional is not a whole
+/// number of quote atoms
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Amounts paid out of or credited by the vault
+    Down,
+    /// Amounts paid into or debited by the vault
+    Up,
+}
+
+impl TradingPair {
+    /// Quote atoms for `quantity` base atoms at `price`, which is quoted per
+    /// `price_scale` base atoms
+    pub fn quote_amount(&self, price: u64, quantity: u64, rounding: Rounding) -> Result<u64> {
+        let product = (price as u128)
+            .checked_mul(quantity as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let scale = self.price_scale as u128;
+        let amount = match rounding {
+            Rounding::Down => product / scale,
+            Rounding::Up => product.div_ceil(scale),
+        };
+        u64::try_from(amount).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// The pair's mints in the order they seed its address, so a market and
+    /// its inverse share one
+    pub fn seed_mints(&self) -> [Pubkey; 2] {
+        [self.low_mint(), self.high_mint()]
+    }
+
+    /// Lesser of the base and quote mint keys
+    pub fn low_mint(&self) -> Pubkey {
+        self.base_mint.min(self.quote_mint)
+    }
+
+    /// Greater of the base and quote mint keys
+    pub fn high_mint(&self) -> Pubkey {
+        self.base_mint.max(self.quote_mint)
+    }
+
+    /// Auction epoch containing `slot`, zero for continuous matching
+    pub fn auction_epoch(&self, slot: u64) -> u64 {
+        slot.checked_div(self.auction_interval_slots).unwrap_or(0)
+    }
+
+    /// Priority stamp of an order submitted in `slot`. Under an auction
+    /// schedule every order of an epoch gets the epoch's first slot, so none
+    /// of them is ahead of another.
+    pub fn order_stamp(&self, slot: u64) -> u64 {
+        match self.auction_interval_slots {
+            0 => slot,
+            interval => slot - slot % interval,
+        }
+    }
+
+    /// Start the auction for the epochs closed before `slot` and return the
+    /// stamp cutoff of the orders it matches. Continuous markets match every
+    /// order; auction markets match once per epoch boundary.
+    pub fn open_auction(&mut self, slot: u64) -> Result<u64> {
+        if self.auction_interval_slots == 0 {
+            return Ok(u64::MAX);
+        }
+        let epoch = self.auction_epoch(slot);
+        require!(
+            epoch > self.last_auction_epoch,
+            ErrorCode::AuctionEpochNotOver
+        );
+        self.last_auction_epoch = epoch;
+        Ok(self.order_stamp(slot))
+    }
+
+    /// Move into `state` begun at `started_slot`, announcing the transition.
+    /// A recurring schedule re-enters a phase in each session.
+    pub fn set_session_state(&mut self, state: SessionState, started_slot: u64) {
+        if state == self.session_state && started_slot == self.session_started_slot {
+            return;
+        }
+        emit!(SessionStateChangedEvent {
+            trading_pair_id: self.trading_pair_id,
+            previous: self.session_state,
+            state,
+            started_slot,
+        });
+        self.session_state = state;
+        self.session_started_slot = started_slot;
+    }
+
+    /// Apply the session schedule at `slot`; a halt holds until the
+    /// authority lifts it
+    pub fn advance_session(&mut self, slot: u64) {
+        if self.session_state == SessionState::Halted || !self.session_schedule.is_set() {
+            return;
+        }
+        let (state, started_slot) = self.session_schedule.state_at(slot);
+        self.set_session_state(state, started_slot);
+    }
+
+    /// Bring the session up to date and check that it permits `action`
+    pub fn enter_session(&mut self, slot: u64, action: SessionAction) -> Result<()> {
+        self.advance_session(slot);
+        require!(
+            self.session_state.permits(action),
+            ErrorCode::SessionActionNotPermitted
+        );
+        Ok(())
+    }
+
+    /// Stamp cutoff of the orders a match at `slot` may fill. A session
+    /// auction uncrosses the orders stamped before it began, once per phase;
+    /// otherwise the pair's auction schedule applies.
+    pub fn match_cutoff(&mut self, slot: u64) -> Result<u64> {
+        if self.session_state.is_auction() {
+            require!(
+                self.uncrossed_session_slot != self.session_started_slot,
+                ErrorCode::AuctionAlreadyUncrossed
+            );
+            self.uncrossed_session_slot = self.session_started_slot;
+            return Ok(self.session_cutoff());
+        }
+        self.open_auction(slot)
+    }
+
+    /// Stamp cutoff of a session auction. Orders are compared by stamp, so
+    /// under an auction schedule the orders stamped in the epoch the phase
+    /// began in wait for the next match.
+    pub fn session_cutoff(&self) -> u64 {
+        self.order_stamp(self.session_started_slot)
+    }
+
+    /// Stamp cutoff a match queued at `slot` would use, without opening an
+    /// auction
+    pub fn pending_cutoff(&self, slot: u64) -> u64 {
+        if self.session_state.is_auction() {
+            self.session_cutoff()
+        } else if self.auction_interval_slots == 0 {
+            u64::MAX
+        } else {
+            self.order_stamp(slot)
+        }
+    }
+
+    /// Whether a taker fee always covers the maker rebate and the referral
+    /// share paid out of it
+    pub fn taker_fee_covers_payouts(&self) -> bool {
+        let taker_fee = self.taker_fee_bps as u64 * 10_000;
+        let payouts = self.maker_rebate_bps as u64 * 10_000
+            + self.taker_fee_bps as u64 * self.referral_share_bps as u64;
+        self.referral_share_bps <= 10_000 && payouts <= taker_fee
+    }
+
+    /// Record fees that reached the fee vault
+    pub fn collect_fees(&mut self, amount: u64) -> Result<()> {
+        self.fees_collected = self
+            .fees_collected
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Record the rounding remainder a settlement left in the quote vault
+    pub fn accrue_dust(&mut self, amount: u64) -> Result<()> {
+        self.quote_dust = self
+            .quote_dust
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Whether `vault` is this pair's base vault (true) or quote vault (false)
+    pub fn vault_is_base(&self, vault: &Pubkey) -> Result<bool> {
+        if *vault == self.base_vault {
+            Ok(true)
+        } else if *vault == self.quote_vault {
+            Ok(false)
+        } else {
+            Err(ErrorCode::InvalidEscrowAccount.into())
+        }
+    }
+
+    /// Check that the client passed the offset derived from this pair's
+    /// computation count and advance the count
+    pub fn claim_computation_offset(
+        &mut self,
+        trading_pair: &Pubkey,
+        computation_offset: u64,
+    ) -> Result<()> {
+        require!(
+            computation_offset == derive_computation_offset(trading_pair, self.computation_count),
+            ErrorCode::InvalidComputationOffset
+        );
+        self.computation_count = self
+            .computation_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Whether to produce an audit record, and the key and nonce to encrypt it
+    /// to. Without an auditor the circuit zeroes the record and it is
+    /// encrypted to the MXE's own key, so no trader's output nonce is spent.
+    pub fn audit_receiver(&mut self, mxe: &MXEAccount) -> Result<(bool, [u8; 32], u128)> {
+        let nonce = self.auditor_nonce;
+        self.auditor_nonce = nonce.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        if self.auditor_pubkey == [0; 32] {
+            return Ok((false, mxe_x25519_pubkey(mxe)?, nonce));
+        }
+        Ok((true, self.auditor_pubkey, nonce))
+    }
+
+    /// Whether a match may be queued in `slot`. A book a match found nothing
+    /// to trade in waits out the match interval, but any change to it can be
+    /// matched at once, so no cranker can hold matching back.
+    pub fn may_match(&self, slot: u64) -> bool {
+        self.book_version != self.empty_match_version
+            || slot
+                >= self
+                    .last_empty_match_slot
+                    .saturating_add(self.match_interval_slots)
+    }
+
+    /// Take the in-flight lock before queueing a computation that rewrites the
+    /// order book, returning the version the computation will be built against
+    pub fn lock_book(&mut self) -> Result<u64> {
+        require!(!self.book_locked, ErrorCode::OrderBookLocked);
+        self.book_locked = true;
+        Ok(self.book_version)
+    }
+
+    /// Accept a callback result only if it was computed against the current
+    /// book version, then bump the version and release the lock
+    pub fn commit_book(&mut self, computed_version: u64) -> Result<()> {
+        require!(self.book_locked, ErrorCode::OrderBookNotLocked);
+        require!(
+            computed_version == self.book_version,
+            ErrorCode::StaleOrderBook
+        );
+        self.book_version = self
+            .book_version
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.book_locked = false;
+        Ok(())
+    }
+}
+
+/// Derive the Arcium computation offset for the `sequence`-th computation queued
+/// by a trading pair, so offsets are unique across pairs and never chosen by clients
+pub fn derive_computation_offset(trading_pair: &Pubkey, sequence: u64) -> u64 {
+    let hash = anchor_lang::solana_program::hash::hashv(&[
+        b"computation_offset",
+        trading_pair.as_ref(),
+        &sequence.to_le_bytes(),
+    ]);
+    let mut offset = [0u8; 8];
+    offset.copy_from_slice(&hash.to_bytes()[..8]);
+    u64::from_le_bytes(offset)
+}
+
+/// x25519 key of the MXE, which audit records go to while auditing is off
+pub fn mxe_x25519_pubkey(mxe: &MXEAccount) -> Result<[u8; 32]> {
+    match &mxe.x25519_pubkey {
+        X25519Pubkey::Set(pubkey) => Ok(*pubkey),
+        _ => err!(ErrorCode::MxeKeyUnset),
+    }
+}
+
+/// Emit a computation's audit record if it was encrypted to the pair's auditor
+pub fn emit_audit_record(
+    trading_pair: &TradingPair,
+    kind: ComputationKind,
+    encryption_key: [u8; 32],
+    nonce: u128,
+    ciphertexts: &[[u8; 32]],
+) {
+    if trading_pair.auditor_pubkey == [0; 32] || encryption_key != trading_pair.auditor_pubkey {
+        return;
+    }
+    emit!(AuditRecordEvent {
+        trading_pair_id: trading_pair.trading_pair_id,
+        book_version: trading_pair.book_version,
+        kind,
+        encryption_key,
+        nonce,
+        ciphertexts: ciphertexts.to_vec(),
+    });
+}
+
+/// Reject Token-2022 mints carrying an extension in UNSUPPORTED_MINT_EXTENSIONS
+/// or one whose behaviour an authority can change under a live market: a
+/// transfer hook, whose extra accounts are not forwarded, or its authority;
+/// a transfer fee authority, which could raise the fee escrow was sized for;
+/// a close authority; and new accounts starting frozen. Extensions newer than
+/// the Token-2022 version the program is built against, pausable mints among
+/// them, fail to parse and are rejected too.
+pub fn validate_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let extensions = state
+        .get_extension_types()
+        .map_err(|_| ErrorCode::UnsupportedMintExtension)?;
+    for extension in extensions {
+        require!(
+            !UNSUPPORTED_MINT_EXTENSIONS.contains(&extension),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    if let Ok(hook) = state.get_extension::<TransferHook>() {
+        require!(
+            Option::<Pubkey>::from(hook.program_id).is_none()
+                && Option::<Pubkey>::from(hook.authority).is_none(),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    if let Ok(config) = state.get_extension::<TransferFeeConfig>() {
+        require!(
+            Option::<Pubkey>::from(config.transfer_fee_config_authority).is_none(),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    if let Ok(close) = state.get_extension::<MintCloseAuthority>() {
+        require!(
+            Option::<Pubkey>::from(close.close_authority).is_none(),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    if let Ok(default_state) = state.get_extension::<DefaultAccountState>() {
+        require!(
+            default_state.state != AccountState::Frozen as u8,
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    Ok(())
+}
+
+/// Whether `mint` is wrapped SOL under SPL Token or Token-2022
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+/// Fee a Token-2022 mint withholds from a transfer of `amount` this epoch
+pub fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(0);
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let Ok(config) = state.get_extension::<TransferFeeConfig>() else {
+        return Ok(0);
+    };
+    config
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or(ErrorCode::MathOverflow.into())
+}
+
+/// Move `amount` with transfer_checked and return what `to` receives after
+/// any transfer fee; `signer_seeds` is empty unless `authority` is a PDA
+pub fn transfer_checked_net<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    let fee = transfer_fee(&mint.to_account_info(), amount)?;
+    let cpi_accounts = TransferChecked {
+        from,
+        mint: mint.to_account_info(),
+        to,
+        authority,
+    };
+    let cpi_ctx =
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)?;
+    Ok(amount - fee)
+}
+
+/// Trader ID the circuits use for a wallet: the first 16 bytes of its public key
+pub fn trader_id(trader: &Pubkey) -> u128 {
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&trader.to_bytes()[..16]);
+    u128::from_le_bytes(id)
+}
+
+/// Per-trader, per-pair account tracking escrowed funds and open orders
+#[account]
+#[derive(InitSpace)]
+pub struct UserAccount {
+    /// Wallet that owns this account
+    pub owner: Pubkey,
+    /// Trading pair this account trades on
+    pub trading_pair: Pubkey,
+    /// Base tokens held in the pair vault for this trader
+    pub base_escrowed: u64,
+    /// Quote tokens held in the pair vault for this trader
+    pub quote_escrowed: u64,
+    /// Portion of base_escrowed reserved by open orders
+    pub base_locked: u64,
+    /// Portion of quote_escrowed reserved by open orders
+    pub quote_locked: u64,
+    /// Number of orders resting in the book
+    pub open_orders: u32,
+    /// x25519 public key the trader encrypts orders with
+    pub encryption_pubkey: [u8; 32],
+    /// Base tokens bought or sold through matched trades
+    pub base_filled: u64,
+    /// Quote tokens paid or received through matched trades
+    pub quote_filled: u64,
+    /// Number of fills the trader took part in
+    pub fill_count: u64,
+    /// PDA bump
+    pub bump: u8,
+    /// Whether the MXE has stored an encrypted volume for this trader
+    pub has_volume: bool,
+    /// Nonce of the MXE-encrypted volume
+    pub volume_nonce: u128,
+    /// MXE-encrypted TraderVolume: the cumulative quote volume selecting the
+    /// trader's fee tier, then the tier discounts they have not claimed
+    pub volume_ciphertexts: [[u8; 32]; 2],
+    /// Referrer account credited with a share of the trader's taker fees,
+    /// kept until the trader clears it
+    pub referrer: Pubkey,
+    /// Computation offset of the trader's latest settle_fills
+    pub settle_offset: u64,
+    /// Slot that settlement was queued in, zero once its callback lands
+    pub settle_queued_slot: u64,
+    /// Quote atoms held back from the fee sweep for the trader's unclaimed
+    /// tier discounts, at the highest tier's rate
+    pub fee_credit_reserve: u64,
+}
+
+impl UserAccount {
+    /// Escrowed balance that is not reserved by open orders
+    pub fn available(&self, is_base: bool) -> u64 {
+        if is_base {
+            self.base_escrowed.saturating_sub(self.base_locked)
+        } else {
+            self.quote_escrowed.saturating_sub(self.quote_locked)
+        }
+    }
+
+    /// Credit tokens moved into the pair vault
+    pub fn deposit(&mut self, is_base: bool, amount: u64) -> Result<()> {
+        let escrowed = if is_base {
+            &mut self.base_escrowed
+        } else {
+            &mut self.quote_escrowed
+        };
+        *escrowed = escrowed.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Debit tokens leaving the pair vault, only from the free balance
+    pub fn withdraw(&mut self, is_base: bool, amount: u64) -> Result<()> {
+        require!(
+            self.available(is_base) >= amount,
+            ErrorCode::InsufficientBalance
+        );
+        if is_base {
+            self.base_escrowed -= amount;
+        } else {
+            self.quote_escrowed -= amount;
+        }
+        Ok(())
+    }
+
+    /// Reserve part of the free balance for an open order
+    pub fn lock(&mut self, is_base: bool, amount: u64) -> Result<()> {
+        require!(
+            self.available(is_base) >= amount,
+            ErrorCode::InsufficientBalance
+        );
+        if is_base {
+            self.base_locked += amount;
+        } else {
+            self.quote_locked += amount;
+        }
+        Ok(())
+    }
+
+    /// Return a reserved amount to the free balance
+    pub fn unlock(&mut self, is_base: bool, amount: u64) -> Result<()> {
+        let locked = if is_base {
+            &mut self.base_locked
+        } else {
+            &mut self.quote_locked
+        };
+        *locked = locked.checked_sub(amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Apply a trader's net movement from a match: debits leave the locked
+    /// balance, credits join the free balance, and escrow left on closed
+    /// orders is unlocked
+    pub fn settle(&mut self, settlement: &Settlement) -> Result<()> {
+        let quote_paid = settlement
+            .quote_debit
+            .checked_add(settlement.buy_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            self.base_locked >= settlement.base_debit && self.quote_locked >= quote_paid,
+            ErrorCode::InsufficientBalance
+        );
+        self.base_locked -= settlement.base_debit;
+        self.quote_locked -= quote_paid;
+        self.base_escrowed = self
+            .base_escrowed
+            .checked_sub(settlement.base_debit)
+            .and_then(|balance| balance.checked_add(settlement.base_credit))
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.quote_escrowed = self
+            .quote_escrowed
+            .checked_sub(quote_paid)
+            .and_then(|balance| balance.checked_add(settlement.quote_credit))
+            .and_then(|balance| balance.checked_sub(settlement.sell_fee))
+            .and_then(|balance| balance.checked_add(settlement.rebate))
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.unlock(true, settlement.base_release)?;
+        self.unlock(false, settlement.quote_release)?;
+
+        self.base_filled = self
+            .base_filled
+            .checked_add(settlement.base_credit + settlement.base_debit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.quote_filled = self
+            .quote_filled
+            .checked_add(settlement.quote_credit + settlement.quote_debit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.fill_count += settlement.fill_count as u64;
+        self.open_orders = self
+            .open_orders
+            .saturating_sub(settlement.orders_closed as u32);
+        Ok(())
+    }
+
+    /// Whether the account holds nothing and can be closed
+    pub fn is_empty(&self) -> bool {
+        self.open_orders == 0
+            && self.base_escrowed == 0
+            && self.quote_escrowed == 0
+            && self.fee_credit_reserve == 0
+    }
+}
+
+/// One match's trade prints, MXE-encrypted until revealed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct TapeEntry {
+    /// Position of the match on the tape
+    pub sequence: u64,
+    /// Unix time the match was recorded
+    pub recorded_at: i64,
+    /// Unix time the entry may be revealed, fixed by the delay at recording
+    pub reveal_after: i64,
+    /// MXE encryption nonce of the batch
+    pub nonce: u128,
+    /// TapeBatch: prices, quantities and timestamps of 5 trades, then trade_count
+    pub ciphertexts: [[u8; 32]; 16],
+    /// Recorded but not yet revealed
+    pub pending: bool,
+}
+
+/// Partner front-end's claim on referral shares of a trading pair's fees
+#[account]
+#[derive(InitSpace)]
+pub struct Referrer {
+    /// Trading pair the referrals are made on
+    pub trading_pair: Pubkey,
+    /// Wallet allowed to claim the accrued shares
+    pub owner: Pubkey,
+    /// Quote atoms accrued and not yet claimed
+    pub accrued: u64,
+    /// Quote atoms claimed over the account's lifetime
+    pub claimed: u64,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl Referrer {
+    /// Credit a referral share collected into the fee vault
+    pub fn accrue(&mut self, amount: u64) -> Result<()> {
+        self.accrued = self
+            .accrued
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Fills of one match, MXE-encrypted until each trader in them has settled
+/// their share with `settle_fills`
+#[account]
+#[derive(InitSpace)]
+pub struct MatchFills {
+    /// Trading pair the match ran on
+    pub trading_pair: Pubkey,
+    /// Position of the match among the pair's recorded matches
+    pub sequence: u64,
+    /// Wallet that queued the match and is refunded the account's rent
+    pub cranker: Pubkey,
+    /// Quote token account the crank reward goes to, the fee vault for none
+    pub crank_reward_account: Pubkey,
+    /// Lamports the cranker posted with the match
+    pub bond: u64,
+    /// Whether the match callback has stored the fills
+    pub recorded: bool,
+    /// IDs of the traders in the fills in ascending order, unused slots zeroed
+    pub participants: [u128; MATCH_PARTICIPANTS],
+    /// Bit i is set once participants[i] has settled
+    pub settled: u16,
+    /// Quote atoms buyers have paid for their fills so far
+    pub quote_paid: u64,
+    /// Quote atoms sellers have received for their fills so far
+    pub quote_received: u64,
+    /// Fees settled so far
+    pub fees: u64,
+    /// Maker rebates settled so far
+    pub rebates: u64,
+    /// Referral shares credited to referrers so far
+    pub referrals: u64,
+    /// Fees held back in the quote vault for tier discounts so far
+    pub credit_reserves: u64,
+    /// MXE encryption nonce of the fills
+    pub nonce: u128,
+    /// MatchFills: 5 trades of (buyer_id, seller_id, price, quantity,
+    /// timestamp), trade_count, then per fill the buyer fee, seller fee,
+    /// rebate, referral, buyer release, seller release, whether the buyer was
+    /// the taker, and whether the buy and sell orders closed
+    pub ciphertexts: [[u8; 32]; MATCH_FILLS_CIPHERTEXTS],
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl MatchFills {
+    /// Position of `trader` among the participants
+    pub fn participant_index(&self, trader: u128) -> Option<usize> {
+        self.participants
+            .iter()
+            .position(|&participant| participant != 0 && participant == trader)
+    }
+
+    /// Whether the participant at `index` has settled
+    pub fn has_settled(&self, index: usize) -> bool {
+        self.settled & (1 << index) != 0
+    }
+
+    /// Whether every participant has settled
+    pub fn is_complete(&self) -> bool {
+        self.participants
+            .iter()
+            .enumerate()
+            .all(|(index, &participant)| participant == 0 || self.has_settled(index))
+    }
+
+    /// Add a trader's settlement to the match totals
+    pub fn accumulate(&mut self, settlement: &Settlement) -> Result<()> {
+        self.quote_paid = self
+            .quote_paid
+            .checked_add(settlement.quote_debit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.quote_received = self
+            .quote_received
+            .checked_add(settlement.quote_credit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.fees = self
+            .fees
+            .checked_add(settlement.buy_fee)
+            .and_then(|fees| fees.checked_add(settlement.sell_fee))
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.rebates = self
+            .rebates
+            .checked_add(settlement.rebate)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.credit_reserves = self
+            .credit_reserves
+            .checked_add(settlement.credit_reserve)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Circuit arguments passing the stored fills back in as `Enc<Mxe, MatchFills>`
+    pub fn arguments(&self) -> Vec<Argument> {
+        let mut args = vec![Argument::PlaintextU128(self.nonce)];
+        let (trades, rest) = self.ciphertexts.split_at(25);
+        for trade in trades.chunks(5) {
+            args.push(Argument::EncryptedU128(trade[0]));
+            args.push(Argument::EncryptedU128(trade[1]));
+            for ciphertext in &trade[2..] {
+                args.push(Argument::EncryptedU64(*ciphertext));
+            }
+        }
+        args.push(Argument::EncryptedU8(rest[0]));
+        for ciphertext in &rest[1..31] {
+            args.push(Argument::EncryptedU64(*ciphertext));
+        }
+        for ciphertext in &rest[31..] {
+            args.push(Argument::EncryptedBool(*ciphertext));
+        }
+        args
+    }
+}
+
+/// Marker reserving a trading pair ID for the market created with it; the
+/// market itself is addressed by its mint pair
+#[account]
+#[derive(InitSpace)]
+pub struct PairIdClaim {
+    /// Trading pair created with the ID
+    pub trading_pair: Pubkey,
+    /// PDA bump
+    pub bump: u8,
+}
+
+/// MXE-encrypted order book of a trading pair, too large for the
+/// TradingPair account. Circuits read it in place and the callbacks of those
+/// that change it write it back.
+#[account]
+#[derive(InitSpace)]
+pub struct OrderBook {
+    /// Order book ciphertexts in the circuit's field order: per order its ID,
+    /// price, quantity, side, trader ID, timestamp, slot, escrow and referral
+    /// flag
+    pub ciphertexts: [[u8; 32]; ORDER_BOOK_CIPHERTEXTS],
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl OrderBook {
+    /// Circuit argument reading the stored book as `Enc<Mxe, OrderBook>`
+    /// ciphertexts, following an `Argument::PlaintextU128` of its nonce
+    pub fn argument(order_book: Pubkey) -> Argument {
+        Argument::Account(order_book, 8, (32 * ORDER_BOOK_CIPHERTEXTS) as u32)
+    }
+}
+
+/// Ring buffer of encrypted match prints that become public after a delay
+#[account]
+#[derive(InitSpace)]
+pub struct TradeTape {
+    /// Trading pair whose matches are recorded
+    pub trading_pair: Pubkey,
+    /// Seconds an entry stays encrypted
+    pub delay_seconds: i64,
+    /// Sequence number the next match will be recorded under
+    pub next_sequence: u64,
+    /// Entries indexed by sequence modulo TAPE_CAPACITY
+    pub entries: [TapeEntry; TAPE_CAPACITY],
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl TradeTape {
+    /// Entry slot for a sequence number
+    pub fn entry(&self, sequence: u64) -> &TapeEntry {
+        &self.entries[(sequence % TAPE_CAPACITY as u64) as usize]
+    }
+
+    /// Mutable entry slot for a sequence number
+    pub fn entry_mut(&mut self, sequence: u64) -> &mut TapeEntry {
+        &mut self.entries[(sequence % TAPE_CAPACITY as u64) as usize]
+    }
+
+    /// Store a match's encrypted prints over the oldest entry, returning
+    /// their sequence number
+    pub fn record(
+        &mut self,
+        nonce: u128,
+        ciphertexts: [[u8; 32]; 16],
+        recorded_at: i64,
+    ) -> Result<u64> {
+        let sequence = self.next_sequence;
+        let reveal_after = recorded_at
+            .checked_add(self.delay_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        *self.entry_mut(sequence) = TapeEntry {
+            sequence,
+            recorded_at,
+            reveal_after,
+            nonce,
+            ciphertexts,
+            pending: true,
+        };
+        self.next_sequence = sequence.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(sequence)
+    }
+}
+
+/// MXE nonce for the fills of the match queued at `computation_offset`, kept
+/// apart from the random nonces the order book is encrypted under
+pub fn fills_nonce(trading_pair: &Pubkey, computation_offset: u64) -> u128 {
+    let hash = anchor_lang::solana_program::hash::hashv(&[
+        b"match_fills",
+        trading_pair.as_ref(),
+        &computation_offset.to_le_bytes(),
+    ]);
+    let mut nonce = [0u8; 16];
+    nonce.copy_from_slice(&hash.to_bytes()[..16]);
+    u128::from_le_bytes(nonce)
+}
+
+/// MXE nonce for the tape batch of the match queued at `computation_offset`,
+/// kept apart from the random nonces the order book is encrypted under
+pub fn tape_nonce(trading_pair: &Pubkey, computation_offset: u64) -> u128 {
+    let hash = anchor_lang::solana_program::hash::hashv(&[
+        b"trade_tape",
+        trading_pair.as_ref(),
+        &computation_offset.to_le_bytes(),
+    ]);
+    let mut nonce = [0u8; 16];
+    nonce.copy_from_slice(&hash.to_bytes()[..16]);
+    u128::from_le_bytes(nonce)
+}
+
+/// Side left with unfilled size at an indicative clearing price
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ImbalanceSide {
+    None,
+    Buy,
+    Sell,
+}
+
+/// Net movement of one trader's vault balances from a match, as revealed by the MXE
+#[derive(Clone, Copy, Default)]
+pub struct Settlement {
+    /// Base tokens bought
+    pub base_credit: u64,
+    /// Quote tokens received for base sold
+    pub quote_credit: u64,
+    /// Base tokens sold, taken from locked escrow
+    pub base_debit: u64,
+    /// Quote tokens paid, taken from locked escrow
+    pub quote_debit: u64,
+    /// Base escrow left on sell orders closed by the match
+    pub base_release: u64,
+    /// Quote escrow left on buy orders closed by the match
+    pub quote_release: u64,
+    /// Number of fills the trader took part in
+    pub fill_count: u8,
+    /// Number of the trader's orders removed from the book
+    pub orders_closed: u8,
+    /// Fees on the trader's buys, taken from locked quote escrow
+    pub buy_fee: u64,
+    /// Fees on the trader's sells, withheld from quote credits
+    pub sell_fee: u64,
+    /// Maker rebates credited to the trader's free quote balance
+    pub rebate: u64,
+    /// Share of the trader's taker fees owed to their referrer
+    pub referral: u64,
+    /// Part of the fees held back for the trader's tier discount
+    pub credit_reserve: u64,
+}
+
+/// Long-term x25519 key a trader registers so the MXE can encrypt results to them
+#[account]
+#[derive(InitSpace)]
+pub struct EncryptionKey {
+    /// Trader that registered the key
+    pub trader: Pubkey,
+    /// Arcis x25519 public key
+    pub pubkey: [u8; 32],
+    /// Number of times the key has been rotated
+    pub key_version: u32,
+    /// Next nonce for outputs the MXE encrypts to this key
+    pub output_nonce: u128,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl EncryptionKey {
+    /// Reserve a fresh nonce for an output encrypted to this key
+    pub fn next_output_nonce(&mut self) -> Result<u128> {
+        let nonce = self.output_nonce;
+        self.output_nonce = nonce.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(nonce)
+    }
+}
+
+/// Per-trader record of the last client nonce used to encrypt order data
+#[account]
+#[derive(InitSpace)]
+pub struct TraderNonce {
+    /// Trader the nonces belong to
+    pub trader: Pubkey,
+    /// Highest client nonce accepted so far
+    pub last_client_nonce: u128,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl TraderNonce {
+    /// Accept a client nonce only if it is strictly greater than every nonce
+    /// used before, so no nonce is ever reused with the same encryption key
+    pub fn consume(&mut self, client_nonce: u128) -> Result<()> {
+        require!(
+            client_nonce > self.last_client_nonce,
+            ErrorCode::ClientNonceReused
+        );
+        self.last_client_nonce = client_nonce;
+        Ok(())
+    }
+}
+
+/// Kind of book-mutating computation tracked by a PendingComputation
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum ComputationKind {
+    InitOrderBook,
+    SubmitOrder,
+    CancelOrder,
+    MatchOrders,
+}
+
+/// Lifecycle of a tracked computation
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum ComputationStatus {
+    /// Nothing in flight
+    Idle,
+    /// Queued and waiting for its callback
+    Queued,
+    /// Callback reported an aborted computation
+    Failed,
+}
+
+/// Book-mutating computation awaiting its callback, one per trading pair
+#[account]
+#[derive(InitSpace)]
+pub struct PendingComputation {
+    /// Trading pair this record belongs to
+    pub trading_pair: Pubkey,
+    /// Current state of the tracked computation
+    pub status: ComputationStatus,
+    /// Which encrypted instruction was queued
+    pub kind: ComputationKind,
+    /// Arcium computation offset
+    pub computation_offset: u64,
+    /// Account that queued the computation
+    pub submitter: Pubkey,
+    /// Slot the computation was queued in
+    pub queued_slot: u64,
+    /// Tokens escrowed for a submitted order, refunded if the submission fails
+    pub escrow_amount: u64,
+    /// Vault holding the escrowed tokens
+    pub escrow_vault: Pubkey,
+    /// Token account the escrow is refunded to
+    pub escrow_refund_account: Pubkey,
+    /// Client x25519 public key of the encrypted order, kept for re-queueing
+    pub client_pubkey: [u8; 32],
+    /// Client nonce of the encrypted order
+    pub client_nonce: u128,
+    /// Encrypted order fields (price, quantity, side)
+    pub ciphertexts: [[u8; 32]; 3],
+    /// Unix time the order was submitted
+    pub timestamp: u64,
+    /// Slot the order was stamped with
+    pub slot: u64,
+    /// Whether the trader had a referrer when the order was submitted
+    pub has_referrer: bool,
+    /// Last auction epoch whose orders a match fills
+    pub auction_epoch: u64,
+    /// Auction epoch the pair had matched before a match opened the next,
+    /// restored if the match is cleared
+    pub previous_auction_epoch: u64,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl PendingComputation {
+    /// Start tracking a newly queued computation
+    pub fn track(
+        &mut self,
+        kind: ComputationKind,
+        computation_offset: u64,
+        submitter: Pubkey,
+        queued_slot: u64,
+    ) {
+        self.status = ComputationStatus::Queued;
+        self.kind = kind;
+        self.computation_offset = computation_offset;
+        self.submitter = submitter;
+        self.queued_slot = queued_slot;
+        self.escrow_amount = 0;
+        self.escrow_vault = Pubkey::default();
+        self.escrow_refund_account = Pubkey::default();
+    }
+
+    /// Record an aborted computation; the book stays locked until it is cleared or re-queued.
+    /// A late abort from a computation that was since cleared or re-queued is rejected.
+    pub fn mark_failed(&mut self, computation_account: &Pubkey) -> Result<()> {
+        require!(
+            self.status == ComputationStatus::Queued,
+            ErrorCode::NoPendingComputation
+        );
+        require_keys_eq!(
+            *computation_account,
+            derive_comp_pda!(self.computation_offset),
+            ErrorCode::ComputationMismatch
+        );
+        self.status = ComputationStatus::Failed;
+        Ok(())
+    }
+
+    /// Stop tracking once the computation has landed or been cleared
+    pub fn complete(&mut self) {
+        self.status = ComputationStatus::Idle;
+        self.escrow_amount = 0;
+    }
+
+    /// Whether the computation failed or has been waiting longer than the timeout
+    pub fn is_recoverable(&self, current_slot: u64) -> bool {
+        match self.status {
+            ComputationStatus::Idle => false,
+            ComputationStatus::Queued => {
+                current_slot >= self.queued_slot.saturating_add(COMPUTATION_TIMEOUT_SLOTS)
+            }
+            ComputationStatus::Failed => true,
+        }
+    }
+}
+
+// Account validation structures for initialization
+#[queue_computation_accounts("init_order_book", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, trading_pair_id: u64)]
+pub struct InitializeTradingPair<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
+    #[account(mut, address = derive_mempool_pda!())]
+    pub mempool_account: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
+    #[account(mut, address = derive_execpool_pda!())]
+    pub executing_pool: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ORDER_BOOK))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TradingPair::INIT_SPACE,
+        seeds = [
+            b"trading_pair",
+            base_mint.key().min(quote_mint.key()).as_ref(),
+            base_mint.key().max(quote_mint.key()).as_ref(),
+        ],
+        bump,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PendingComputation::INIT_SPACE,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TradeTape::INIT_SPACE,
+        seeds = [b"trade_tape", trading_pair.key().as_ref()],
+        bump,
+    )]
+    pub trade_tape: Box<Account<'info, TradeTape>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + OrderBook::INIT_SPACE,
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    /// Claims the trading pair ID so no two markets share it
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PairIdClaim::INIT_SPACE,
+        seeds = [b"pair_id", trading_pair_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub pair_id_claim: Box<Account<'info, PairIdClaim>>,
+    pub base_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(constraint = quote_mint.key() != base_mint.key() @ ErrorCode::IdenticalMints)]
+    pub quote_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+// Callback account structure
+#[callback_accounts("init_order_book")]
+#[derive(Accounts)]
+pub struct InitOrderBookCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ORDER_BOOK))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+    /// CHECK: Computation the callback reports on, checked against the
+    /// pending computation's offset before a failure is recorded
+    pub computation_account: UncheckedAccount<'info>,
+}
 
 // Submit order accounts
 #[queue_computation_accounts("submit_order", payer)]
 #[derive(Accounts)]
 #[instruction(computation_offset: u64, trading_pair_id: u64)]
-pub struct SubmitOrder<'info> {
+pub struct SubmitOrder<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
+    #[account(mut, address = derive_mempool_pda!())]
+    pub mempool_account: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
+    #[account(mut, address = derive_execpool_pda!())]
+    pub executing_pool: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBMIT_ORDER))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TraderNonce::INIT_SPACE,
+        seeds = [b"trader_nonce", payer.key().as_ref()],
+        bump,
+    )]
+    pub trader_nonce: Box<Account<'info, TraderNonce>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserAccount::INIT_SPACE,
+        seeds = [b"user_account", trading_pair.key().as_ref(), payer.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Box<Account<'info, UserAccount>>,
+    #[account(
+        mut,
+        seeds = [b"encryption_key", payer.key().as_ref()],
+        bump = encryption_key.bump,
+    )]
+    pub encryption_key: Box<Account<'info, EncryptionKey>>,
+    // User's token accounts for balance validation
+    /// CHECK: Optional user base token account - when provided, validated in submit_order by deserializing
+    /// as TokenAccount and checking mint matches trading_pair.base_mint and owner matches payer
+    pub user_base_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Optional user quote token account - when provided, validated in submit_order by deserializing
+    /// as TokenAccount and checking mint matches trading_pair.quote_mint and owner matches payer
+    pub user_quote_token_account: Option<UncheckedAccount<'info>>,
+    /// Token account the escrow is taken from, required when escrow_amount > 0
+    #[account(mut)]
+    pub escrow_source: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Pair vault receiving the escrow, must be trading_pair.base_vault or quote_vault
+    #[account(mut)]
+    pub escrow_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Mint of the escrowed tokens, required when escrow_amount > 0
+    pub escrow_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// Referrer receiving a share of the taker fees on this and the trader's
+    /// later orders; must match any referrer the trader already has
+    #[account(
+        constraint = referrer.trading_pair == trading_pair.key() @ ErrorCode::InvalidReferrer,
+        constraint = referrer.owner != payer.key() @ ErrorCode::InvalidReferrer,
+    )]
+    pub referrer: Option<Box<Account<'info, Referrer>>>,
+}
+
+#[callback_accounts("submit_order")]
+#[derive(Accounts)]
+pub struct SubmitOrderCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBMIT_ORDER))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+    #[account(
+        mut,
+        seeds = [
+            b"user_account",
+            trading_pair.key().as_ref(),
+            pending_computation.submitter.as_ref(),
+        ],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    /// CHECK: Computation the callback reports on, checked against the
+    /// pending computation's offset before a failure is recorded
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+// Cancel order accounts
+#[queue_computation_accounts("cancel_order", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, trading_pair_id: u64)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
+    #[account(mut, address = derive_mempool_pda!())]
+    pub mempool_account: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
+    #[account(mut, address = derive_execpool_pda!())]
+    pub executing_pool: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CANCEL_ORDER))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TraderNonce::INIT_SPACE,
+        seeds = [b"trader_nonce", payer.key().as_ref()],
+        bump,
+    )]
+    pub trader_nonce: Box<Account<'info, TraderNonce>>,
+    #[account(
+        mut,
+        seeds = [b"user_account", trading_pair.key().as_ref(), payer.key().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Box<Account<'info, UserAccount>>,
+    #[account(
+        mut,
+        seeds = [b"encryption_key", payer.key().as_ref()],
+        bump = encryption_key.bump,
+    )]
+    pub encryption_key: Box<Account<'info, EncryptionKey>>,
+}
+
+#[callback_accounts("cancel_order")]
+#[derive(Accounts)]
+pub struct CancelOrderCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CANCEL_ORDER))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+    #[account(
+        mut,
+        seeds = [
+            b"user_account",
+            trading_pair.key().as_ref(),
+            pending_computation.submitter.as_ref(),
+        ],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    /// CHECK: Computation the callback reports on, checked against the
+    /// pending computation's offset before a failure is recorded
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+// Match orders accounts
+#[queue_computation_accounts("match_orders", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, trading_pair_id: u64)]
+pub struct MatchOrders<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
+    #[account(mut, address = derive_mempool_pda!())]
+    pub mempool_account: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
+    #[account(mut, address = derive_execpool_pda!())]
+    pub executing_pool: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_MATCH_ORDERS))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    #[account(
+        seeds = [b"trade_tape", trading_pair.key().as_ref()],
+        bump = trade_tape.bump,
+    )]
+    pub trade_tape: Box<Account<'info, TradeTape>>,
+    #[account(
+        init,
+        space = 8 + MatchFills::INIT_SPACE,
+        payer = payer,
+        seeds = [
+            b"match_fills",
+            trading_pair.key().as_ref(),
+            trading_pair.match_count.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub match_fills: Box<Account<'info, MatchFills>>,
+    /// Quote token account the crank reward is paid to; the pair's vaults
+    /// must exist to pay it from
+    #[account(
+        constraint = crank_reward_account.mint == trading_pair.quote_mint @ ErrorCode::InvalidTokenAccount,
+        constraint = crank_reward_account.key() != trading_pair.quote_vault @ ErrorCode::InvalidTokenAccount,
+        constraint = trading_pair.fee_vault != Pubkey::default() @ ErrorCode::InvalidEscrowAccount,
+    )]
+    pub crank_reward_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+}
+
+#[callback_accounts("match_orders")]
+#[derive(Accounts)]
+pub struct MatchOrdersCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_MATCH_ORDERS))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+    #[account(
+        mut,
+        seeds = [b"trade_tape", trading_pair.key().as_ref()],
+        bump = trade_tape.bump,
+    )]
+    pub trade_tape: Box<Account<'info, TradeTape>>,
+    /// CHECK: Computation the callback reports on, checked against the
+    /// pending computation's offset before a failure is recorded
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"match_fills",
+            trading_pair.key().as_ref(),
+            trading_pair.match_count.to_le_bytes().as_ref(),
+        ],
+        bump = match_fills.bump,
+    )]
+    pub match_fills: Box<Account<'info, MatchFills>>,
+    /// CHECK: Cranker of the match, refunded the MatchFills rent when there is
+    /// nothing to settle
+    #[account(mut, address = match_fills.cranker)]
+    pub cranker: UncheckedAccount<'info>,
+    /// CHECK: Pair authority, paid the bond of a match that produced no trades
+    #[account(mut, address = trading_pair.authority)]
+    pub authority: UncheckedAccount<'info>,
+}
+
+#[queue_computation_accounts("settle_fills", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, trading_pair_id: u64, sequence: u64)]
+pub struct SettleFills<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
+    #[account(mut, address = derive_mempool_pda!())]
+    pub mempool_account: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
+    #[account(mut, address = derive_execpool_pda!())]
+    pub executing_pool: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SETTLE_FILLS))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        seeds = [b"match_fills", trading_pair.key().as_ref(), sequence.to_le_bytes().as_ref()],
+        bump = match_fills.bump,
+    )]
+    pub match_fills: Box<Account<'info, MatchFills>>,
+    /// User account of the trader being settled
+    #[account(
+        mut,
+        constraint = user_account.trading_pair == trading_pair.key() @ ErrorCode::NotAMatchParticipant,
+    )]
+    pub user_account: Box<Account<'info, UserAccount>>,
+    /// Key the trader's fill report is encrypted to
+    #[account(
+        mut,
+        seeds = [b"encryption_key", user_account.owner.as_ref()],
+        bump = encryption_key.bump,
+    )]
+    pub encryption_key: Account<'info, EncryptionKey>,
+}
+
+#[callback_accounts("settle_fills")]
+#[derive(Accounts)]
+pub struct SettleFillsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SETTLE_FILLS))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(mut, has_one = trading_pair)]
+    pub match_fills: Box<Account<'info, MatchFills>>,
+    #[account(mut, has_one = trading_pair)]
+    pub user_account: Box<Account<'info, UserAccount>>,
+    /// CHECK: Computation the callback reports on, checked against the
+    /// trader's latest settlement before it is applied
+    pub computation_account: UncheckedAccount<'info>,
+    /// CHECK: Cranker of the match, refunded the MatchFills rent
+    #[account(mut, address = match_fills.cranker)]
+    pub cranker: UncheckedAccount<'info>,
+}
+
+#[queue_computation_accounts("claim_fee_credit", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, trading_pair_id: u64)]
+pub struct ClaimFeeCredit<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
+    #[account(mut, address = derive_mempool_pda!())]
+    pub mempool_account: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
+    #[account(mut, address = derive_execpool_pda!())]
+    pub executing_pool: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLAIM_FEE_CREDIT))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"user_account", trading_pair.key().as_ref(), payer.key().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Box<Account<'info, UserAccount>>,
+}
+
+#[callback_accounts("claim_fee_credit")]
+#[derive(Accounts)]
+pub struct ClaimFeeCreditCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CLAIM_FEE_CREDIT))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(mut, has_one = trading_pair)]
+    pub user_account: Box<Account<'info, UserAccount>>,
+    /// CHECK: Computation the callback reports on, checked against the
+    /// trader's latest claim before it is applied
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(mut, address = trading_pair.quote_vault @ ErrorCode::InvalidEscrowAccount)]
+    pub quote_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = trading_pair.fee_vault @ ErrorCode::InvalidEscrowAccount)]
+    pub fee_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = trading_pair.quote_mint @ ErrorCode::InvalidTokenAccount)]
+    pub quote_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = trading_pair.quote_token_program)]
+    pub quote_token_program: Interface<'info, TokenInterface>,
+}
+
+#[queue_computation_accounts("query_my_orders", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, trading_pair_id: u64)]
+pub struct QueryMyOrders<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
+    #[account(mut, address = derive_mempool_pda!())]
+    pub mempool_account: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
+    #[account(mut, address = derive_execpool_pda!())]
+    pub executing_pool: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_QUERY_MY_ORDERS))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"encryption_key", payer.key().as_ref()],
+        bump = encryption_key.bump,
+    )]
+    pub encryption_key: Box<Account<'info, EncryptionKey>>,
+}
+
+#[callback_accounts("query_my_orders")]
+#[derive(Accounts)]
+pub struct QueryMyOrdersCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_QUERY_MY_ORDERS))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub trading_pair: Account<'info, TradingPair>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct ConfigureDepth<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct ConfigureFees<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct WithdrawFees<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(mut, address = trading_pair.fee_vault @ ErrorCode::InvalidEscrowAccount)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.mint == trading_pair.quote_mint @ ErrorCode::InvalidTokenAccount,
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = trading_pair.quote_mint @ ErrorCode::InvalidTokenAccount)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = trading_pair.quote_token_program)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct AdvanceSession<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct RegisterReferrer<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Referrer::INIT_SPACE,
+        seeds = [b"referrer", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub referrer: Account<'info, Referrer>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct ClaimReferralFees<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"referrer", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump = referrer.bump,
+    )]
+    pub referrer: Account<'info, Referrer>,
+    #[account(mut, address = trading_pair.fee_vault @ ErrorCode::InvalidEscrowAccount)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.mint == trading_pair.quote_mint @ ErrorCode::InvalidTokenAccount,
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = trading_pair.quote_mint @ ErrorCode::InvalidTokenAccount)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = trading_pair.quote_token_program)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct ClearReferrer<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"user_account", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct SetAuditor<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct ConfigureTape<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"trade_tape", trading_pair.key().as_ref()],
+        bump = trade_tape.bump,
+    )]
+    pub trade_tape: Box<Account<'info, TradeTape>>,
+}
+
+#[queue_computation_accounts("reveal_tape", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, trading_pair_id: u64)]
+pub struct RevealTape<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     #[account(
@@ -553,7 +5370,7 @@ pub struct SubmitOrder<'info> {
     /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
     #[account(mut, address = derive_comp_pda!(computation_offset))]
     pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBMIT_ORDER))]
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_TAPE))]
     pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
     #[account(mut, address = derive_cluster_pda!(mxe_account))]
     pub cluster_account: Account<'info, Cluster>,
@@ -565,24 +5382,99 @@ pub struct SubmitOrder<'info> {
     pub arcium_program: Program<'info, Arcium>,
     #[account(
         mut,
-        seeds = [b"trading_pair", trading_pair_id.to_le_bytes().as_ref()],
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
         bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
     )]
     pub trading_pair: Account<'info, TradingPair>,
-    // User's token accounts for balance validation
-    /// CHECK: Optional user base token account - when provided, validated in submit_order by deserializing
-    /// as TokenAccount and checking mint matches trading_pair.base_mint and owner matches payer
-    pub user_base_token_account: Option<UncheckedAccount<'info>>,
-    /// CHECK: Optional user quote token account - when provided, validated in submit_order by deserializing
-    /// as TokenAccount and checking mint matches trading_pair.quote_mint and owner matches payer
-    pub user_quote_token_account: Option<UncheckedAccount<'info>>,
+    #[account(
+        seeds = [b"trade_tape", trading_pair.key().as_ref()],
+        bump = trade_tape.bump,
+    )]
+    pub trade_tape: Box<Account<'info, TradeTape>>,
 }
 
-#[callback_accounts("submit_order")]
+#[callback_accounts("reveal_tape")]
 #[derive(Accounts)]
-pub struct SubmitOrderCallback<'info> {
+pub struct RevealTapeCallback<'info> {
     pub arcium_program: Program<'info, Arcium>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBMIT_ORDER))]
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_TAPE))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"trade_tape", trading_pair.key().as_ref()],
+        bump = trade_tape.bump,
+    )]
+    pub trade_tape: Box<Account<'info, TradeTape>>,
+}
+
+#[queue_computation_accounts("reveal_depth", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, trading_pair_id: u64)]
+pub struct RevealDepth<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+    /// CHECK: Verified by Arcium macros via derive_mempool_pda!() address constraint
+    #[account(mut, address = derive_mempool_pda!())]
+    pub mempool_account: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_execpool_pda!() address constraint
+    #[account(mut, address = derive_execpool_pda!())]
+    pub executing_pool: UncheckedAccount<'info>,
+    /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_DEPTH))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+}
+
+#[callback_accounts("reveal_depth")]
+#[derive(Accounts)]
+pub struct RevealDepthCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_DEPTH))]
     pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
     #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
     /// CHECK: Validated by Arcium program through address constraint
@@ -591,11 +5483,10 @@ pub struct SubmitOrderCallback<'info> {
     pub trading_pair: Account<'info, TradingPair>,
 }
 
-// Cancel order accounts
-#[queue_computation_accounts("cancel_order", payer)]
+#[queue_computation_accounts("indicative_price", payer)]
 #[derive(Accounts)]
 #[instruction(computation_offset: u64, trading_pair_id: u64)]
-pub struct CancelOrder<'info> {
+pub struct IndicativePrice<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     #[account(
@@ -618,7 +5509,7 @@ pub struct CancelOrder<'info> {
     /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
     #[account(mut, address = derive_comp_pda!(computation_offset))]
     pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CANCEL_ORDER))]
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INDICATIVE_PRICE))]
     pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
     #[account(mut, address = derive_cluster_pda!(mxe_account))]
     pub cluster_account: Account<'info, Cluster>,
@@ -629,31 +5520,343 @@ pub struct CancelOrder<'info> {
     pub system_program: Program<'info, System>,
     pub arcium_program: Program<'info, Arcium>,
     #[account(
-        mut,
-        seeds = [b"trading_pair", trading_pair_id.to_le_bytes().as_ref()],
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+}
+
+#[callback_accounts("indicative_price")]
+#[derive(Accounts)]
+pub struct IndicativePriceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_INDICATIVE_PRICE))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Validated by Arcium program through address constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub trading_pair: Account<'info, TradingPair>,
+}
+
+// Encryption key registry accounts
+#[derive(Accounts)]
+pub struct RegisterEncryptionKey<'info> {
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = trader,
+        space = 8 + EncryptionKey::INIT_SPACE,
+        seeds = [b"encryption_key", trader.key().as_ref()],
+        bump,
+    )]
+    pub encryption_key: Account<'info, EncryptionKey>,
+    pub system_program: Program<'info, System>,
+}
+
+// Vault initialization accounts
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct InitializeVaults<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(address = trading_pair.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = trading_pair.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"base_vault", trading_pair.key().as_ref()],
+        bump,
+        token::mint = base_mint,
+        token::authority = trading_pair,
+        token::token_program = base_token_program,
+    )]
+    pub base_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"quote_vault", trading_pair.key().as_ref()],
+        bump,
+        token::mint = quote_mint,
+        token::authority = trading_pair,
+        token::token_program = quote_token_program,
+    )]
+    pub quote_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"fee_vault", trading_pair.key().as_ref()],
+        bump,
+        token::mint = quote_mint,
+        token::authority = trading_pair,
+        token::token_program = quote_token_program,
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = trading_pair.base_token_program)]
+    pub base_token_program: Interface<'info, TokenInterface>,
+    #[account(address = trading_pair.quote_token_program)]
+    pub quote_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// User account balance accounts
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + UserAccount::INIT_SPACE,
+        seeds = [b"user_account", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        constraint = source.owner == owner.key() @ ErrorCode::InvalidTokenAccount,
+        constraint = source.mint == vault.mint @ ErrorCode::InvalidTokenAccount,
+    )]
+    pub source: InterfaceAccount<'info, TokenAccount>,
+    /// Pair vault receiving the deposit, must be trading_pair.base_vault or quote_vault
+    #[account(mut)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = vault.mint @ ErrorCode::InvalidTokenAccount)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct Withdraw<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"user_account", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    /// Pair vault the withdrawal is paid from, must be trading_pair.base_vault or quote_vault
+    #[account(mut)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.mint == vault.mint @ ErrorCode::InvalidTokenAccount,
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = vault.mint @ ErrorCode::InvalidTokenAccount)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct DepositSol<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + UserAccount::INIT_SPACE,
+        seeds = [b"user_account", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    /// Pair vault holding wrapped SOL, base or quote; checked against the
+    /// pair's vaults by the instruction
+    #[account(mut, constraint = is_native_mint(&vault.mint) @ ErrorCode::NotNativeSolPair)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = *vault.to_account_info().owner)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct WithdrawSol<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
+    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"user_account", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    /// Pair vault holding wrapped SOL, base or quote; checked against the
+    /// pair's vaults by the instruction
+    #[account(mut, constraint = is_native_mint(&vault.mint) @ ErrorCode::NotNativeSolPair)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = vault.mint @ ErrorCode::InvalidTokenAccount)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// Temporary wrapped SOL account, closed to the owner within the instruction
+    #[account(
+        init,
+        payer = owner,
+        seeds = [b"sol_unwrap", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = trading_pair,
+        token::token_program = token_program,
+    )]
+    pub unwrap_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = *vault.to_account_info().owner)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(trading_pair_id: u64)]
+pub struct CloseUserAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
         bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
     )]
     pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"user_account", trading_pair.key().as_ref(), owner.key().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
 }
 
-#[callback_accounts("cancel_order")]
+// Pending computation recovery accounts
 #[derive(Accounts)]
-pub struct CancelOrderCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CANCEL_ORDER))]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: Validated by Arcium program through address constraint
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
+#[instruction(trading_pair_id: u64)]
+pub struct ClearPendingComputation<'info> {
+    pub caller: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
+        bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
+    )]
     pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Account<'info, PendingComputation>,
+    /// Vault holding the escrow, required when the failed submission escrowed tokens
+    #[account(mut)]
+    pub escrow_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Token account recorded as the escrow refund destination
+    #[account(mut)]
+    pub escrow_refund_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Mint of the escrowed tokens
+    pub escrow_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    /// Submitter's account the escrow was credited to
+    #[account(
+        mut,
+        seeds = [
+            b"user_account",
+            trading_pair.key().as_ref(),
+            pending_computation.submitter.as_ref(),
+        ],
+        bump = user_account.bump,
+    )]
+    pub user_account: Option<Account<'info, UserAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// Fills account of a timed-out match, required to clear one
+    #[account(
+        mut,
+        seeds = [
+            b"match_fills",
+            trading_pair.key().as_ref(),
+            trading_pair.match_count.to_le_bytes().as_ref(),
+        ],
+        bump = match_fills.bump,
+    )]
+    pub match_fills: Option<Box<Account<'info, MatchFills>>>,
+    /// CHECK: Cranker of the timed-out match, checked against match_fills.cranker
+    #[account(mut)]
+    pub cranker: Option<UncheckedAccount<'info>>,
 }
 
-// Match orders accounts
-#[queue_computation_accounts("match_orders", payer)]
+#[queue_computation_accounts("submit_order", payer)]
 #[derive(Accounts)]
 #[instruction(computation_offset: u64, trading_pair_id: u64)]
-pub struct MatchOrders<'info> {
+pub struct RequeueOrder<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     #[account(
@@ -676,7 +5879,7 @@ pub struct MatchOrders<'info> {
     /// CHECK: Verified by Arcium macros via derive_comp_pda!() address constraint
     #[account(mut, address = derive_comp_pda!(computation_offset))]
     pub computation_account: UncheckedAccount<'info>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_MATCH_ORDERS))]
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBMIT_ORDER))]
     pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
     #[account(mut, address = derive_cluster_pda!(mxe_account))]
     pub cluster_account: Account<'info, Cluster>,
@@ -688,23 +5891,38 @@ pub struct MatchOrders<'info> {
     pub arcium_program: Program<'info, Arcium>,
     #[account(
         mut,
-        seeds = [b"trading_pair", trading_pair_id.to_le_bytes().as_ref()],
+        seeds = [
+            b"trading_pair",
+            trading_pair.low_mint().as_ref(),
+            trading_pair.high_mint().as_ref(),
+        ],
         bump = trading_pair.bump,
+        constraint = trading_pair.trading_pair_id == trading_pair_id @ ErrorCode::InvalidTradingPairId,
     )]
     pub trading_pair: Account<'info, TradingPair>,
-}
-
-#[callback_accounts("match_orders")]
-#[derive(Accounts)]
-pub struct MatchOrdersCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_MATCH_ORDERS))]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: Validated by Arcium program through address constraint
-    pub instructions_sysvar: AccountInfo<'info>,
-    #[account(mut)]
-    pub trading_pair: Account<'info, TradingPair>,
+    #[account(
+        seeds = [b"order_book", trading_pair.key().as_ref()],
+        bump = order_book.bump,
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+    #[account(
+        mut,
+        seeds = [b"pending_computation", trading_pair.key().as_ref()],
+        bump = pending_computation.bump,
+    )]
+    pub pending_computation: Box<Account<'info, PendingComputation>>,
+    #[account(
+        mut,
+        seeds = [b"user_account", trading_pair.key().as_ref(), payer.key().as_ref()],
+        bump = user_account.bump,
+    )]
+    pub user_account: Box<Account<'info, UserAccount>>,
+    #[account(
+        mut,
+        seeds = [b"encryption_key", payer.key().as_ref()],
+        bump = encryption_key.bump,
+    )]
+    pub encryption_key: Box<Account<'info, EncryptionKey>>,
 }
 
 // Computation definition initialization accounts
@@ -764,10 +5982,96 @@ pub struct InitMatchOrdersCompDef<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[init_computation_definition_accounts("query_my_orders", payer)]
+#[derive(Accounts)]
+pub struct InitQueryMyOrdersCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: Computation definition account will be initialized by Arcium's init_comp_def function
+    #[account(mut)]
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("reveal_depth", payer)]
+#[derive(Accounts)]
+pub struct InitRevealDepthCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: Computation definition account will be initialized by Arcium's init_comp_def function
+    #[account(mut)]
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("indicative_price", payer)]
+#[derive(Accounts)]
+pub struct InitIndicativePriceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: Computation definition account will be initialized by Arcium's init_comp_def function
+    #[account(mut)]
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("reveal_tape", payer)]
+#[derive(Accounts)]
+pub struct InitRevealTapeCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: Computation definition account will be initialized by Arcium's init_comp_def function
+    #[account(mut)]
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("settle_fills", payer)]
+#[derive(Accounts)]
+pub struct InitSettleFillsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: Computation definition account will be initialized by Arcium's init_comp_def function
+    #[account(mut)]
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("claim_fee_credit", payer)]
+#[derive(Accounts)]
+pub struct InitClaimFeeCreditCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// CHECK: Computation definition account will be initialized by Arcium's init_comp_def function
+    #[account(mut)]
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
 // Trade execution accounts
 #[derive(Accounts)]
 #[instruction(buyer_id: u128, seller_id: u128, trade_price: u64, trade_quantity: u64)]
 pub struct ExecuteTrade<'info> {
+    #[account(mut)]
+    pub trading_pair: Account<'info, TradingPair>,
     #[account(mut)]
     pub buyer: Signer<'info>,
     #[account(mut)]
@@ -788,7 +6092,17 @@ pub struct ExecuteTrade<'info> {
     /// checking mint matches trading_pair.quote_mint, owner matches seller, and sufficient balance
     #[account(mut)]
     pub seller_quote_account: UncheckedAccount<'info>,
-    pub token_program: Program<'info, Token>,
+    /// Pair quote vault receiving the rounding dust
+    #[account(mut, address = trading_pair.quote_vault @ ErrorCode::InvalidEscrowAccount)]
+    pub quote_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = trading_pair.base_mint @ ErrorCode::InvalidTokenAccount)]
+    pub base_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = trading_pair.quote_mint @ ErrorCode::InvalidTokenAccount)]
+    pub quote_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(address = trading_pair.base_token_program)]
+    pub base_token_program: Interface<'info, TokenInterface>,
+    #[account(address = trading_pair.quote_token_program)]
+    pub quote_token_program: Interface<'info, TokenInterface>,
 }
 
 // Events
@@ -801,21 +6115,275 @@ pub struct TradingPairInitializedEvent {
 #[event]
 pub struct OrderSubmittedEvent {
     pub trading_pair_id: u64,
+    /// Auction epoch the order was stamped in, zero for continuous matching
+    pub auction_epoch: u64,
     pub order_book_nonce: u128,
     pub total_orders: u64,
+    pub book_version: u64,
+    pub accepted: bool,
 }
 
 #[event]
 pub struct OrderCancelledEvent {
     pub trading_pair_id: u64,
     pub order_book_nonce: u128,
+    pub book_version: u64,
+    pub cancelled: bool,
 }
 
 #[event]
 pub struct OrdersMatchedEvent {
     pub trading_pair_id: u64,
+    /// Last auction epoch whose orders the match filled, zero for
+    /// continuous matching
+    pub auction_epoch: u64,
     pub match_nonce: u128,
     pub timestamp: u64,
+    pub book_version: u64,
+}
+
+#[event]
+pub struct OrderReceiptEvent {
+    pub trading_pair_id: u64,
+    pub trader: Pubkey,
+    pub encryption_key: [u8; 32],
+    pub nonce: u128,
+    /// OrderReceipt: order_id, accepted, rejection reason
+    pub ciphertexts: [[u8; 32]; 3],
+}
+
+#[event]
+pub struct EncryptionKeyRegisteredEvent {
+    pub trader: Pubkey,
+    pub pubkey: [u8; 32],
+    pub key_version: u32,
+}
+
+#[event]
+pub struct EscrowDepositedEvent {
+    pub trading_pair_id: u64,
+    pub owner: Pubkey,
+    pub is_base: bool,
+    pub amount: u64,
+}
+
+#[event]
+pub struct EscrowWithdrawnEvent {
+    pub trading_pair_id: u64,
+    pub owner: Pubkey,
+    pub is_base: bool,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ComputationFailedEvent {
+    pub trading_pair_id: u64,
+    pub computation_offset: u64,
+    pub kind: ComputationKind,
+}
+
+#[event]
+pub struct PendingComputationClearedEvent {
+    pub trading_pair_id: u64,
+    pub computation_offset: u64,
+    pub kind: ComputationKind,
+    pub escrow_refunded: u64,
+}
+
+#[event]
+pub struct OrderRequeuedEvent {
+    pub trading_pair_id: u64,
+    pub computation_offset: u64,
+    pub book_version: u64,
+}
+
+#[event]
+pub struct FillReportEvent {
+    pub trading_pair_id: u64,
+    /// Match the report settles
+    pub sequence: u64,
+    pub encryption_key: [u8; 32],
+    pub nonce: u128,
+    /// FillReport: 5 trades of (buyer_id, seller_id, price, quantity, timestamp), then trade_count
+    pub ciphertexts: [[u8; 32]; 26],
+}
+
+#[event]
+pub struct MatchRecordedEvent {
+    pub trading_pair_id: u64,
+    pub sequence: u64,
+    pub trade_count: u8,
+    /// IDs of the traders left to settle, ascending and zero-padded
+    pub participants: [u128; MATCH_PARTICIPANTS],
+}
+
+#[event]
+pub struct MyOrdersEvent {
+    pub trading_pair_id: u64,
+    pub book_version: u64,
+    pub encryption_key: [u8; 32],
+    pub nonce: u128,
+    /// MyOrders: 5 orders of (order_id, price, quantity, side), then count and total
+    pub ciphertexts: [[u8; 32]; 22],
+}
+
+#[event]
+pub struct DepthSnapshotEvent {
+    pub trading_pair_id: u64,
+    pub book_version: u64,
+    pub band_width: u64,
+    pub lot_size: u64,
+    pub min_orders: u8,
+    pub mid_price: u64,
+    pub bid_depth: [u64; 4],
+    pub ask_depth: [u64; 4],
+}
+
+#[event]
+pub struct IndicativePriceEvent {
+    pub trading_pair_id: u64,
+    pub book_version: u64,
+    pub price: u64,
+    pub crossed: bool,
+    pub imbalance: ImbalanceSide,
+}
+
+#[event]
+pub struct FeesConfiguredEvent {
+    pub trading_pair_id: u64,
+    pub maker_fee_bps: u16,
+    pub taker_fee_bps: u16,
+    pub maker_rebate_bps: u16,
+}
+
+#[event]
+pub struct FeeTiersConfiguredEvent {
+    pub trading_pair_id: u64,
+    pub fee_tiers: [FeeTier; FEE_TIERS],
+}
+
+#[event]
+pub struct SessionConfiguredEvent {
+    pub trading_pair_id: u64,
+    pub schedule: SessionSchedule,
+}
+
+#[event]
+pub struct SessionStateChangedEvent {
+    pub trading_pair_id: u64,
+    pub previous: SessionState,
+    pub state: SessionState,
+    pub started_slot: u64,
+}
+
+#[event]
+pub struct AuctionConfiguredEvent {
+    pub trading_pair_id: u64,
+    pub interval_slots: u64,
+    pub auction_epoch: u64,
+}
+
+#[event]
+pub struct CrankConfiguredEvent {
+    pub trading_pair_id: u64,
+    pub match_interval_slots: u64,
+    pub crank_reward: u64,
+    pub match_bond: u64,
+}
+
+#[event]
+pub struct CrankRewardedEvent {
+    pub trading_pair_id: u64,
+    pub cranker: Pubkey,
+    pub reward_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct MatchBondForfeitedEvent {
+    pub trading_pair_id: u64,
+    pub cranker: Pubkey,
+    /// Lamports paid to the pair authority
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReferralsConfiguredEvent {
+    pub trading_pair_id: u64,
+    pub referral_share_bps: u16,
+}
+
+#[event]
+pub struct ReferralFeesClaimedEvent {
+    pub trading_pair_id: u64,
+    pub referrer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FeeCreditClaimedEvent {
+    pub trading_pair_id: u64,
+    pub owner: Pubkey,
+    /// Tier discounts added to the trader's free quote balance
+    pub amount: u64,
+}
+
+#[event]
+pub struct FeesWithdrawnEvent {
+    pub trading_pair_id: u64,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AuditorUpdatedEvent {
+    pub trading_pair_id: u64,
+    pub auditor_pubkey: [u8; 32],
+}
+
+#[event]
+pub struct AuditRecordEvent {
+    pub trading_pair_id: u64,
+    pub book_version: u64,
+    pub kind: ComputationKind,
+    pub encryption_key: [u8; 32],
+    pub nonce: u128,
+    /// An Order for submissions and cancellations, a FillReport of all trades for matches
+    pub ciphertexts: Vec<[u8; 32]>,
+}
+
+#[event]
+pub struct TapeRecordedEvent {
+    pub trading_pair_id: u64,
+    pub sequence: u64,
+    pub reveal_after: i64,
+}
+
+#[event]
+pub struct TapeEntryDroppedEvent {
+    pub trading_pair_id: u64,
+    /// Unrevealed entry a newer match was recorded over
+    pub sequence: u64,
+}
+
+#[event]
+pub struct TapePrintEvent {
+    pub trading_pair_id: u64,
+    pub sequence: u64,
+    pub recorded_at: i64,
+    pub prices: [u64; 5],
+    pub quantities: [u64; 5],
+    pub timestamps: [u64; 5],
+    pub trade_count: u8,
+}
+
+#[event]
+pub struct TradesDisclosedEvent {
+    pub trading_pair_id: u64,
+    pub book_version: u64,
+    pub prices: [u64; 5],
+    pub quantities: [u64; 5],
+    pub trade_count: u8,
 }
 
 #[event]
@@ -824,6 +6392,18 @@ pub struct TradeExecutedEvent {
     pub seller_id: u128,
     pub price: u64,
     pub quantity: u64,
+    /// Quote atoms paid by the buyer
+    pub quote_amount: u64,
+    /// Part of `quote_amount` kept by the quote vault as rounding dust
+    pub dust: u64,
+    /// Base atoms the buyer received after any transfer fee
+    pub base_received: u64,
+    /// Quote atoms the seller received after any transfer fee
+    pub quote_received: u64,
+    /// Decimals of the base mint, for displaying `quantity` and `base_received`
+    pub base_decimals: u8,
+    /// Decimals of the quote mint, for displaying the quote amounts and fees
+    pub quote_decimals: u8,
     pub timestamp: u64,
 }
 
@@ -848,4 +6428,96 @@ pub enum ErrorCode {
     InsufficientBalance,
     #[msg("Invalid trading pair ID")]
     InvalidTradingPairId,
+    #[msg("Order book has a computation in flight")]
+    OrderBookLocked,
+    #[msg("Order book is not locked for a computation")]
+    OrderBookNotLocked,
+    #[msg("Computation result was built against a stale order book")]
+    StaleOrderBook,
+    #[msg("No computation is pending")]
+    NoPendingComputation,
+    #[msg("Pending computation has neither failed nor timed out")]
+    ComputationNotRecoverable,
+    #[msg("Only order submissions can be re-queued")]
+    CannotRequeue,
+    #[msg("Only the original submitter can re-queue this computation")]
+    UnauthorizedSubmitter,
+    #[msg("Invalid escrow account")]
+    InvalidEscrowAccount,
+    #[msg("Computation offset does not match the one derived from the trading pair")]
+    InvalidComputationOffset,
+    #[msg("Client nonce must be greater than the last one used by this trader")]
+    ClientNonceReused,
+    #[msg("User account still has open orders or escrowed funds")]
+    UserAccountNotEmpty,
+    #[msg("Invalid encryption key")]
+    InvalidEncryptionKey,
+    #[msg("Settlement accounts do not match the trader or the trading pair")]
+    InvalidSettlementAccounts,
+    #[msg("Order page is out of range")]
+    InvalidOrderPage,
+    #[msg("Signer is not the trading pair authority")]
+    Unauthorized,
+    #[msg("Depth bands and lot size must be non-zero and bands must aggregate two or more orders")]
+    InvalidDepthConfig,
+    #[msg("Depth disclosure is disabled for this trading pair")]
+    DepthDisabled,
+    #[msg("Depth was revealed too recently")]
+    DepthRateLimited,
+    #[msg("Book produced no trades at its last match and has not changed since")]
+    MatchRateLimited,
+    #[msg("The current auction epoch has already been matched")]
+    AuctionEpochNotOver,
+    #[msg("Action is not permitted in the current session phase")]
+    SessionActionNotPermitted,
+    #[msg("Session phases must be scheduled in order from a non-zero slot and fit in the period")]
+    InvalidSessionSchedule,
+    #[msg("Trade tape entry not found or already revealed")]
+    TapeEntryNotFound,
+    #[msg("Trade tape entry is still within its disclosure delay")]
+    TapeEntryTooRecent,
+    #[msg("Tape delay cannot be negative")]
+    InvalidTapeDelay,
+    #[msg("Tick size, lot size, order size range or price scale is invalid for the mints")]
+    InvalidMarketParams,
+    #[msg("Base and quote mints must differ")]
+    IdenticalMints,
+    #[msg("Mint uses a Token-2022 extension the program cannot support")]
+    UnsupportedMintExtension,
+    #[msg("Vault does not hold native SOL")]
+    NotNativeSolPair,
+    #[msg("Fee rates exceed the maximum or the maker rebate is not covered by the taker fee")]
+    InvalidFeeRate,
+    #[msg("Maker rebates and referral shares exceed the fees they are paid from")]
+    RebateExceedsFees,
+    #[msg("Referrer account does not belong to this trading pair or trader")]
+    InvalidReferrer,
+    #[msg("Fee tiers must have non-decreasing thresholds and discounts of at most 100%")]
+    InvalidFeeTiers,
+    #[msg("Callback belongs to a computation other than the pending one")]
+    ComputationMismatch,
+    #[msg("MXE has no x25519 key set yet")]
+    MxeKeyUnset,
+    #[msg("Trader has no fills to settle in this match")]
+    NotAMatchParticipant,
+    #[msg("Trader has already settled this match")]
+    AlreadySettled,
+    #[msg("Trader already has a settlement waiting for its callback")]
+    SettlementInFlight,
+    #[msg("Indicative price was revealed too recently")]
+    IndicativePriceRateLimited,
+    #[msg("Order stamp is later than the current slot")]
+    InvalidOrderStamp,
+    #[msg("Trader has no fee credit to claim")]
+    NoFeeCredit,
+    #[msg("Trader already has a different referrer; clear it first")]
+    ReferrerAlreadySet,
+    #[msg("Referrer cannot be cleared while the trader has open orders")]
+    ReferredOrdersOpen,
+    #[msg("The current auction phase has already been uncrossed")]
+    AuctionAlreadyUncrossed,
+    #[msg("Match fills account or its cranker does not belong to the pending match")]
+    InvalidMatchFills,
+    #[msg("Crank reward or match bond exceeds its maximum")]
+    InvalidCrankConfig,
 }
\ No newline at end of file
@@ -1,15 +1,29 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { PublicKey, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  PublicKey,
+  LAMPORTS_PER_SOL,
+  Keypair,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
 import {
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
   createMint,
   createAccount,
   mintTo,
-  getAccount
+  transfer,
+  getAccount,
+  getMintLen,
+  NATIVE_MINT,
+  createInitializeMintInstruction,
+  createInitializeMintCloseAuthorityInstruction,
+  createInitializeTransferFeeConfigInstruction,
 } from "@solana/spl-token";
 import { ConfHide } from "../target/types/conf_hide";
-import { randomBytes } from "crypto";
+import { createHash, randomBytes } from "crypto";
 import {
   awaitComputationFinalization,
   getArciumEnv,
@@ -220,21 +234,79 @@ describe("ConfHide - Privacy Trading Platform", () => {
     );
     console.log("Match orders comp def initialized:", initMatchOrdersSig);
 
+    const initQueryMyOrdersSig = await initCompDef(
+      program,
+      payer,
+      "query_my_orders",
+      "initQueryMyOrdersCompDef"
+    );
+    console.log("Query my orders comp def initialized:", initQueryMyOrdersSig);
+
+    const initRevealDepthSig = await initCompDef(
+      program,
+      payer,
+      "reveal_depth",
+      "initRevealDepthCompDef"
+    );
+    console.log("Reveal depth comp def initialized:", initRevealDepthSig);
+
+    const initIndicativePriceSig = await initCompDef(
+      program,
+      payer,
+      "indicative_price",
+      "initIndicativePriceCompDef"
+    );
+    console.log("Indicative price comp def initialized:", initIndicativePriceSig);
+
+    const initRevealTapeSig = await initCompDef(
+      program,
+      payer,
+      "reveal_tape",
+      "initRevealTapeCompDef"
+    );
+    console.log("Reveal tape comp def initialized:", initRevealTapeSig);
+
+    const initSettleFillsSig = await initCompDef(
+      program,
+      payer,
+      "settle_fills",
+      "initSettleFillsCompDef"
+    );
+    console.log("Settle fills comp def initialized:", initSettleFillsSig);
+
+    const initClaimFeeCreditSig = await initCompDef(
+      program,
+      payer,
+      "claim_fee_credit",
+      "initClaimFeeCreditCompDef"
+    );
+    console.log("Claim fee credit comp def initialized:", initClaimFeeCreditSig);
+
     // Initialize trading pair
     console.log("Creating SOL/USDC trading pair...");
     const tradingPairId = new anchor.BN(1);
-    const [tradingPairPDA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("trading_pair"), tradingPairId.toArrayLike(Buffer, "le", 8)],
-      program.programId
-    );
+    const tradingPairPDA = tradingPairAddress(baseMint, quoteMint);
 
-    const pairComputationOffset = new anchor.BN(randomBytes(8), "hex");
+    const pairComputationOffset = deriveComputationOffset(tradingPairPDA, new anchor.BN(0));
     const mxeNonce = new anchor.BN(randomBytes(16), "hex");
 
     const initEventPromise = awaitEvent("tradingPairInitializedEvent");
 
     const pairSig = await program.methods
-      .initializeTradingPair(pairComputationOffset, tradingPairId, mxeNonce)
+      .initializeTradingPair(
+        pairComputationOffset,
+        tradingPairId,
+        mxeNonce,
+        { private: {} },
+        {
+          tickSize: new anchor.BN(1_000), // 0.001 USDC
+          lotSize: new anchor.BN(1_000), // 0.000001 tokens, so notionals can be fractional
+          minOrderSize: new anchor.BN(1_000_000),
+          priceScale: new anchor.BN(1_000_000_000), // prices are per whole base token
+          maxOrderPrice: new anchor.BN(1_000_000_000_000), // 1M USDC
+          maxOrderQuantity: new anchor.BN(1_000_000_000_000_000), // 1M tokens
+        }
+      )
       .accountsPartial({
         tradingPair: tradingPairPDA,
         baseMint: baseMint,
@@ -270,10 +342,148 @@ describe("ConfHide - Privacy Trading Platform", () => {
     expect(initEvent.tradingPairId.toString()).to.equal(tradingPairId.toString());
     console.log("Trading pair created with encrypted order book");
 
+    // The inverse market is the same account, so it cannot be created again
+    // under another ID
+    const inverseOffset = deriveComputationOffset(tradingPairPDA, new anchor.BN(1));
+    let inverseCreated = true;
+    try {
+      await program.methods
+        .initializeTradingPair(
+          inverseOffset,
+          new anchor.BN(2),
+          mxeNonce,
+          { private: {} },
+          {
+            tickSize: new anchor.BN(1),
+            lotSize: new anchor.BN(1),
+            minOrderSize: new anchor.BN(1),
+            priceScale: new anchor.BN(1_000_000),
+            maxOrderPrice: new anchor.BN(1_000_000_000),
+            maxOrderQuantity: new anchor.BN(1_000_000_000),
+          }
+        )
+        .accountsPartial({
+          tradingPair: tradingPairPDA,
+          baseMint: quoteMint,
+          quoteMint: baseMint,
+          computationAccount: getComputationAccAddress(program.programId, inverseOffset),
+          clusterAccount: clusterAccount,
+          mxeAccount: getMXEAccAddress(program.programId),
+          mempoolAccount: getMempoolAccAddress(program.programId),
+          executingPool: getExecutingPoolAccAddress(program.programId),
+          compDefAccount: getCompDefAccAddress(
+            program.programId,
+            Buffer.from(getCompDefAccOffset("init_order_book")).readUInt32LE()
+          ),
+          payer: payer.publicKey,
+        })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+    } catch (error) {
+      inverseCreated = false;
+    }
+    expect(inverseCreated).to.equal(false);
+
+    // Token-2022 mints with a fixed transfer fee are accepted; ones whose fee
+    // or lifetime an authority still controls are not
+    const feeMint = await createToken2022Mint(payer, null, false);
+    const feeAuthorityMint = await createToken2022Mint(payer, payer.publicKey, false);
+    const closableMint = await createToken2022Mint(payer, null, true);
+    for (const [id, mint] of [
+      [3, feeAuthorityMint],
+      [4, closableMint],
+    ] as const) {
+      let created = true;
+      try {
+        await queueTradingPair(payer, new anchor.BN(id), mint, quoteMint);
+      } catch (error) {
+        created = false;
+      }
+      expect(created).to.equal(false);
+    }
+    const feePairEventPromise = awaitEvent("tradingPairInitializedEvent");
+    const feePairOffset = await queueTradingPair(payer, new anchor.BN(5), feeMint, quoteMint);
+    await awaitComputationFinalization(provider, feePairOffset, program.programId, "confirmed");
+    const feePairEvent = await feePairEventPromise;
+    expect(feePairEvent.tradingPairId.toString()).to.equal("5");
+
+    // SOL can be either side of a pair: lamports deposited into a SOL quote
+    // vault are wrapped and credited, and withdraw unwrapped
+    const solPairId = new anchor.BN(6);
+    const solPair = tradingPairAddress(baseMint, NATIVE_MINT);
+    const solPairOffset = await queueTradingPair(payer, solPairId, baseMint, NATIVE_MINT);
+    await awaitComputationFinalization(provider, solPairOffset, program.programId, "confirmed");
+    const [solQuoteVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("quote_vault"), solPair.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initializeVaults(solPairId)
+      .accountsPartial({
+        payer: payer.publicKey,
+        tradingPair: solPair,
+        baseMint,
+        quoteMint: NATIVE_MINT,
+        baseTokenProgram: TOKEN_PROGRAM_ID,
+        quoteTokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    const [solUserAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_account"), solPair.toBuffer(), payer.publicKey.toBuffer()],
+      program.programId
+    );
+    const wrapLamports = new anchor.BN(LAMPORTS_PER_SOL / 2);
+    await program.methods
+      .depositSol(solPairId, wrapLamports)
+      .accountsPartial({
+        owner: payer.publicKey,
+        tradingPair: solPair,
+        vault: solQuoteVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    const wrapped = await program.account.userAccount.fetch(solUserAccount);
+    expect(wrapped.quoteEscrowed.toString()).to.equal(wrapLamports.toString());
+    expect(wrapped.baseEscrowed.toString()).to.equal("0");
+    expect((await getAccount(provider.connection, solQuoteVault)).amount.toString()).to.equal(
+      wrapLamports.toString()
+    );
+
+    const lamportsBefore = await provider.connection.getBalance(payer.publicKey);
+    await program.methods
+      .withdrawSol(solPairId, wrapLamports)
+      .accountsPartial({
+        owner: payer.publicKey,
+        tradingPair: solPair,
+        vault: solQuoteVault,
+        mint: NATIVE_MINT,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    const unwrapped = await program.account.userAccount.fetch(solUserAccount);
+    expect(unwrapped.quoteEscrowed.toString()).to.equal("0");
+    expect((await getAccount(provider.connection, solQuoteVault)).amount.toString()).to.equal("0");
+    // The payer gets the lamports back, less at most the transaction fee
+    const lamportsAfter = await provider.connection.getBalance(payer.publicKey);
+    expect(lamportsAfter - lamportsBefore).to.be.greaterThan(wrapLamports.toNumber() - 10_000);
+
+    // Register the trader's long-term key so the MXE can encrypt order receipts to it
+    const traderPrivateKey = x25519.utils.randomSecretKey();
+    const traderPublicKey = x25519.getPublicKey(traderPrivateKey);
+    await program.methods
+      .registerEncryptionKey(Array.from(traderPublicKey))
+      .accounts({ trader: payer.publicKey })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    console.log("Registered trader encryption key");
+
     // Submit buy order
     console.log("Submitting encrypted buy order...");
-    const buyComputationOffset = new anchor.BN(randomBytes(8), "hex");
-    const buyClientNonce = randomBytes(16);
+    const buyComputationOffset = await nextComputationOffset(program, tradingPairPDA);
+    const buyClientNonce = nextClientNonce();
     const buyPrivateKey = x25519.utils.randomSecretKey();
     const buyPublicKey = x25519.getPublicKey(buyPrivateKey);
     const buyClientPubkey = Array.from(buyPublicKey);
@@ -289,11 +499,11 @@ describe("ConfHide - Privacy Trading Platform", () => {
       BigInt(buyPrice.toString()),
       BigInt(buyQuantity.toString()),
       BigInt(isBuy ? 1 : 0),
-      BigInt(new anchor.BN(payer.publicKey.toBuffer()).shln(96).toString()),
     ];
     const ciphertext = buyCipher.encrypt(plaintext, buyClientNonce);
 
     const buyOrderEventPromise = awaitEvent("orderSubmittedEvent");
+    const buyReceiptEventPromise = awaitEvent("orderReceiptEvent");
 
     const buySig = await program.methods
       .submitOrder(
@@ -304,7 +514,7 @@ describe("ConfHide - Privacy Trading Platform", () => {
         Array.from(ciphertext[0]),
         Array.from(ciphertext[1]),
         Array.from(ciphertext[2]),
-        Array.from(ciphertext[3])
+        new anchor.BN(0) // no escrow
       )
       .accountsPartial({
         tradingPair: tradingPairPDA,
@@ -338,12 +548,24 @@ describe("ConfHide - Privacy Trading Platform", () => {
     const buyOrderEvent = await buyOrderEventPromise;
     expect(buyOrderEvent.tradingPairId.toString()).to.equal(tradingPairId.toString());
     expect(buyOrderEvent.totalOrders.toString()).to.equal("1");
+
+    // The receipt is encrypted to the registered key: order ID, accepted, rejection reason
+    const buyReceiptEvent = await buyReceiptEventPromise;
+    const receiptCipher = new RescueCipher(
+      x25519.getSharedSecret(traderPrivateKey, mxePublicKey)
+    );
+    const [, buyAccepted, buyRejectReason] = receiptCipher.decrypt(
+      buyReceiptEvent.ciphertexts,
+      buyReceiptEvent.nonce.toArrayLike(Buffer, "le", 16)
+    );
+    expect(Number(buyAccepted)).to.equal(1);
+    expect(Number(buyRejectReason)).to.equal(0);
     console.log("✅ Buy order submitted and encrypted");
 
     // Submit sell order
     console.log("Submitting encrypted sell order...");
-    const sellComputationOffset = new anchor.BN(randomBytes(8), "hex");
-    const sellClientNonce = randomBytes(16);
+    const sellComputationOffset = await nextComputationOffset(program, tradingPairPDA);
+    const sellClientNonce = nextClientNonce();
     const sellPrivateKey = x25519.utils.randomSecretKey();
     const sellPublicKey = x25519.getPublicKey(sellPrivateKey);
     const sellClientPubkey = Array.from(sellPublicKey);
@@ -359,7 +581,6 @@ describe("ConfHide - Privacy Trading Platform", () => {
       BigInt(sellPrice.toString()),
       BigInt(sellQuantity.toString()),
       BigInt(isSell ? 1 : 0),
-      BigInt(new anchor.BN(payer.publicKey.toBuffer()).shln(96).toString()),
     ];
     const sellCiphertext = sellCipher.encrypt(sellPlaintext, sellClientNonce);
 
@@ -374,7 +595,7 @@ describe("ConfHide - Privacy Trading Platform", () => {
         Array.from(sellCiphertext[0]),
         Array.from(sellCiphertext[1]),
         Array.from(sellCiphertext[2]),
-        Array.from(sellCiphertext[3])
+        new anchor.BN(0) // no escrow
       )
       .accountsPartial({
         tradingPair: tradingPairPDA,
@@ -409,15 +630,78 @@ describe("ConfHide - Privacy Trading Platform", () => {
     expect(sellOrderEvent.totalOrders.toString()).to.equal("2");
     console.log("✅ Sell order submitted and encrypted");
 
+    // Rounding dust from settlement is paid into the pair's quote vault, and
+    // private matches are rewarded out of the fee vault
+    const [baseVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("base_vault"), tradingPairPDA.toBuffer()],
+      program.programId
+    );
+    const [quoteVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("quote_vault"), tradingPairPDA.toBuffer()],
+      program.programId
+    );
+    const [feeVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_vault"), tradingPairPDA.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initializeVaults(tradingPairId)
+      .accountsPartial({
+        payer: payer.publicKey,
+        tradingPair: tradingPairPDA,
+        baseMint,
+        quoteMint,
+        baseVault: baseVaultPDA,
+        quoteVault: quoteVaultPDA,
+        feeVault: feeVaultPDA,
+        baseTokenProgram: TOKEN_PROGRAM_ID,
+        quoteTokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+
+    // Private fills pay no fee, so the 1 USDC crank reward comes out of what
+    // the fee vault holds
+    await program.methods
+      .configureCrank(tradingPairId, new anchor.BN(0), new anchor.BN(1_000_000), new anchor.BN(0))
+      .accountsPartial({ authority: payer.publicKey, tradingPair: tradingPairPDA })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    await transfer(
+      provider.connection,
+      payer,
+      trader2QuoteAccount,
+      feeVaultPDA,
+      trader2,
+      5_000_000
+    );
+    const crankRewardAccount = await createAccount(
+      provider.connection,
+      payer,
+      quoteMint,
+      payer.publicKey
+    );
+
     // Match orders
     console.log("Triggering private order matching...");
-    const matchComputationOffset = new anchor.BN(randomBytes(8), "hex");
+    const matchComputationOffset = await nextComputationOffset(program, tradingPairPDA);
     const matchEventPromise = awaitEvent("ordersMatchedEvent");
+    const matchRecordedEventPromise = awaitEvent("matchRecordedEvent");
+
+    const [payerUserAccountPDA] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("user_account"),
+        tradingPairPDA.toBuffer(),
+        payer.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
 
     const matchSig = await program.methods
       .matchOrders(matchComputationOffset, tradingPairId)
       .accountsPartial({
         tradingPair: tradingPairPDA,
+        crankRewardAccount,
         computationAccount: getComputationAccAddress(
           program.programId,
           matchComputationOffset
@@ -449,6 +733,133 @@ describe("ConfHide - Privacy Trading Platform", () => {
     expect(matchEvent.tradingPairId.toString()).to.equal(tradingPairId.toString());
     console.log("Orders matched privately - trades revealed");
 
+    // Only the traders in the fills are revealed; the payer was on both sides
+    const matchRecordedEvent = await matchRecordedEventPromise;
+    expect(matchRecordedEvent.tradeCount).to.equal(1);
+    const participants = matchRecordedEvent.participants.filter(
+      (id) => !id.isZero()
+    );
+    expect(participants.length).to.equal(1);
+    expect(participants[0].toArrayLike(Buffer, "le", 16)).to.deep.equal(
+      payer.publicKey.toBuffer().subarray(0, 16)
+    );
+
+    // Anyone may settle a participant; the report goes to their registered key
+    console.log("Settling the payer's fills...");
+    const settleComputationOffset = await nextComputationOffset(program, tradingPairPDA);
+    const fillReportEventPromise = awaitEvent("fillReportEvent");
+    const crankRewardedEventPromise = awaitEvent("crankRewardedEvent");
+
+    const settleSig = await program.methods
+      .settleFills(settleComputationOffset, tradingPairId, matchRecordedEvent.sequence)
+      .accountsPartial({
+        tradingPair: tradingPairPDA,
+        userAccount: payerUserAccountPDA,
+        computationAccount: getComputationAccAddress(
+          program.programId,
+          settleComputationOffset
+        ),
+        clusterAccount: clusterAccount,
+        mxeAccount: getMXEAccAddress(program.programId),
+        mempoolAccount: getMempoolAccAddress(program.programId),
+        executingPool: getExecutingPoolAccAddress(program.programId),
+        compDefAccount: getCompDefAccAddress(
+          program.programId,
+          Buffer.from(getCompDefAccOffset("settle_fills")).readUInt32LE()
+        ),
+        payer: payer.publicKey,
+      })
+      .signers([payer])
+      .rpc({ skipPreflight: true, commitment: "confirmed" });
+    console.log("Settle fills sig:", settleSig);
+
+    await awaitComputationFinalization(
+      provider,
+      settleComputationOffset,
+      program.programId,
+      "confirmed"
+    );
+
+    // FillReport: 5 trades of (buyer_id, seller_id, price, quantity, timestamp), then trade_count
+    const fillReportEvent = await fillReportEventPromise;
+    expect(fillReportEvent.sequence.toString()).to.equal(
+      matchRecordedEvent.sequence.toString()
+    );
+    const reportCipher = new RescueCipher(
+      x25519.getSharedSecret(traderPrivateKey, mxePublicKey)
+    );
+    const report = reportCipher.decrypt(
+      fillReportEvent.ciphertexts,
+      fillReportEvent.nonce.toArrayLike(Buffer, "le", 16)
+    );
+    expect(Number(report[25])).to.equal(1);
+    // Both 100 and 95 clear 5 tokens with 5 left over; the first candidate wins
+    expect(report[2].toString()).to.equal(buyPrice.toString());
+    expect(report[3].toString()).to.equal(sellQuantity.toString());
+    console.log("✅ Fill report delivered to the filled order's owner");
+
+    // Settling the last participant paid the cranker
+    const crankRewardedEvent = await crankRewardedEventPromise;
+    expect(crankRewardedEvent.amount.toString()).to.equal("1000000");
+    expect(crankRewardedEvent.rewardAccount.toBase58()).to.equal(crankRewardAccount.toBase58());
+    expect(
+      (await getAccount(provider.connection, crankRewardAccount)).amount.toString()
+    ).to.equal("1000000");
+
+    // The sell order filled in full and left the book, even on a private pair
+    const settledUserAccount = await program.account.userAccount.fetch(
+      payerUserAccountPDA
+    );
+    expect(settledUserAccount.openOrders).to.equal(1);
+
+    // Query the payer's remaining resting orders
+    console.log("Querying own resting orders...");
+    const queryComputationOffset = await nextComputationOffset(program, tradingPairPDA);
+    const myOrdersEventPromise = awaitEvent("myOrdersEvent");
+
+    const querySig = await program.methods
+      .queryMyOrders(queryComputationOffset, tradingPairId, 0)
+      .accountsPartial({
+        tradingPair: tradingPairPDA,
+        computationAccount: getComputationAccAddress(
+          program.programId,
+          queryComputationOffset
+        ),
+        clusterAccount: clusterAccount,
+        mxeAccount: getMXEAccAddress(program.programId),
+        mempoolAccount: getMempoolAccAddress(program.programId),
+        executingPool: getExecutingPoolAccAddress(program.programId),
+        compDefAccount: getCompDefAccAddress(
+          program.programId,
+          Buffer.from(getCompDefAccOffset("query_my_orders")).readUInt32LE()
+        ),
+        payer: payer.publicKey,
+      })
+      .signers([payer])
+      .rpc({ skipPreflight: true, commitment: "confirmed" });
+    console.log("Query my orders sig:", querySig);
+
+    await awaitComputationFinalization(
+      provider,
+      queryComputationOffset,
+      program.programId,
+      "confirmed"
+    );
+
+    const myOrdersEvent = await myOrdersEventPromise;
+    expect(myOrdersEvent.tradingPairId.toString()).to.equal(tradingPairId.toString());
+    const queryCipher = new RescueCipher(
+      x25519.getSharedSecret(traderPrivateKey, mxePublicKey)
+    );
+    const myOrders = queryCipher.decrypt(
+      myOrdersEvent.ciphertexts,
+      myOrdersEvent.nonce.toArrayLike(Buffer, "le", 16)
+    );
+    const [pageCount, totalOrders] = myOrders.slice(20);
+    expect(Number(pageCount)).to.equal(Math.min(Number(totalOrders), 5));
+    expect(Number(totalOrders)).to.be.at.most(2);
+    console.log("Resting orders for payer:", totalOrders.toString());
+
     // Execute trade
     console.log("Executing token transfers...");
     const buyerId = new anchor.BN(1);
@@ -461,13 +872,18 @@ describe("ConfHide - Privacy Trading Platform", () => {
     const tradeSig = await program.methods
       .executeTrade(buyerId, sellerId, tradePrice, tradeQuantity)
       .accountsPartial({
+        tradingPair: tradingPairPDA,
         buyer: trader1.publicKey,
         seller: trader2.publicKey,
         buyerBaseAccount: trader1BaseAccount,
         buyerQuoteAccount: trader1QuoteAccount,
         sellerBaseAccount: trader2BaseAccount,
         sellerQuoteAccount: trader2QuoteAccount,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        quoteVault: quoteVaultPDA,
+        baseMint,
+        quoteMint,
+        baseTokenProgram: TOKEN_PROGRAM_ID,
+        quoteTokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([trader1, trader2])
       .rpc({ commitment: "confirmed" });
@@ -477,8 +893,53 @@ describe("ConfHide - Privacy Trading Platform", () => {
     const tradeEvent = await tradeEventPromise;
     expect(tradeEvent.buyerId.toString()).to.equal(buyerId.toString());
     expect(tradeEvent.sellerId.toString()).to.equal(sellerId.toString());
+    // 95 USDC per token * 5 tokens divides exactly, so no dust
+    expect(tradeEvent.quoteAmount.toString()).to.equal("475000000");
+    expect(tradeEvent.dust.toString()).to.equal("0");
+    // Legacy SPL mints charge no transfer fee, and execute_trade cannot tell
+    // the taker so it charges no protocol fee either
+    expect(tradeEvent.baseReceived.toString()).to.equal(tradeQuantity.toString());
+    expect(tradeEvent.quoteReceived.toString()).to.equal("475000000");
+    expect(tradeEvent.baseDecimals).to.equal(9);
+    expect(tradeEvent.quoteDecimals).to.equal(6);
     console.log("Trade executed with token transfers");
 
+    // 95.001 USDC per token * 0.001001 tokens is 95096.001 quote atoms: the
+    // buyer pays 95097, the seller receives 95096 and the vault keeps 1
+    const dustBefore = (await program.account.tradingPair.fetch(tradingPairPDA))
+      .quoteDust;
+    const dustEventPromise = awaitEvent("tradeExecutedEvent");
+    await program.methods
+      .executeTrade(
+        buyerId,
+        sellerId,
+        new anchor.BN(95_001_000),
+        new anchor.BN(1_001_000)
+      )
+      .accountsPartial({
+        tradingPair: tradingPairPDA,
+        buyer: trader1.publicKey,
+        seller: trader2.publicKey,
+        buyerBaseAccount: trader1BaseAccount,
+        buyerQuoteAccount: trader1QuoteAccount,
+        sellerBaseAccount: trader2BaseAccount,
+        sellerQuoteAccount: trader2QuoteAccount,
+        quoteVault: quoteVaultPDA,
+        baseMint,
+        quoteMint,
+        baseTokenProgram: TOKEN_PROGRAM_ID,
+        quoteTokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([trader1, trader2])
+      .rpc({ commitment: "confirmed" });
+    const dustEvent = await dustEventPromise;
+    expect(dustEvent.quoteAmount.toString()).to.equal("95097");
+    expect(dustEvent.quoteReceived.toString()).to.equal("95096");
+    expect(dustEvent.dust.toString()).to.equal("1");
+    const dustAfter = (await program.account.tradingPair.fetch(tradingPairPDA))
+      .quoteDust;
+    expect(dustAfter.sub(dustBefore).toNumber()).to.equal(1);
+
     // Verify balances
     const trader1Base = await getAccount(provider.connection, trader1BaseAccount);
     const trader1Quote = await getAccount(provider.connection, trader1QuoteAccount);
@@ -500,6 +961,608 @@ describe("ConfHide - Privacy Trading Platform", () => {
     console.log("\n🎉 All tests passed! ConfHide privacy trading platform is working!");
   });
 
+  it("settles a disclosing market at base fee rates with claimable tier discounts", async () => {
+    const payer = readKpJson(`${os.homedir()}/.config/solana/id.json`);
+    const mxePublicKey = await getMXEPublicKeyWithRetry(provider, program.programId);
+
+    const baseMint = await createMint(provider.connection, payer, payer.publicKey, null, 9);
+    const quoteMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    const payerBaseAccount = await createAccount(
+      provider.connection,
+      payer,
+      baseMint,
+      payer.publicKey
+    );
+    const payerQuoteAccount = await createAccount(
+      provider.connection,
+      payer,
+      quoteMint,
+      payer.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      baseMint,
+      payerBaseAccount,
+      payer.publicKey,
+      100_000_000_000
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      quoteMint,
+      payerQuoteAccount,
+      payer.publicKey,
+      10_000_000_000
+    );
+
+    const tradingPairId = new anchor.BN(7);
+    const tradingPair = tradingPairAddress(baseMint, quoteMint);
+    const pairOffset = await queueTradingPair(payer, tradingPairId, baseMint, quoteMint, {
+      priceAndSize: {},
+    });
+    await awaitComputationFinalization(provider, pairOffset, program.programId, "confirmed");
+
+    const [baseVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("base_vault"), tradingPair.toBuffer()],
+      program.programId
+    );
+    const [quoteVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("quote_vault"), tradingPair.toBuffer()],
+      program.programId
+    );
+    const [feeVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_vault"), tradingPair.toBuffer()],
+      program.programId
+    );
+    const [userAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user_account"), tradingPair.toBuffer(), payer.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initializeVaults(tradingPairId)
+      .accountsPartial({
+        payer: payer.publicKey,
+        tradingPair,
+        baseMint,
+        quoteMint,
+        baseTokenProgram: TOKEN_PROGRAM_ID,
+        quoteTokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+
+    // A 1% taker fee, a fifth of it for a referred taker's referrer; the
+    // payer's volume puts them in the 20% tier, while the top tier takes 50% off
+    await program.methods
+      .configureFees(tradingPairId, 0, 100, 0)
+      .accountsPartial({ authority: payer.publicKey, tradingPair })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .configureReferrals(tradingPairId, 2_000)
+      .accountsPartial({ authority: payer.publicKey, tradingPair })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .configureFeeTiers(tradingPairId, [
+        { minVolume: new anchor.BN(0), discountBps: 2_000 },
+        { minVolume: new anchor.BN(1_000_000_000_000), discountBps: 5_000 },
+        { minVolume: new anchor.BN(1_000_000_000_000), discountBps: 5_000 },
+      ])
+      .accountsPartial({ authority: payer.publicKey, tradingPair })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+
+    const traderPrivateKey = x25519.utils.randomSecretKey();
+    await program.methods
+      .registerEncryptionKey(Array.from(x25519.getPublicKey(traderPrivateKey)))
+      .accounts({ trader: payer.publicKey })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+
+    const queueAccounts = (offset: anchor.BN, circuit: string) => ({
+      computationAccount: getComputationAccAddress(program.programId, offset),
+      clusterAccount: clusterAccount,
+      mxeAccount: getMXEAccAddress(program.programId),
+      mempoolAccount: getMempoolAccAddress(program.programId),
+      executingPool: getExecutingPoolAccAddress(program.programId),
+      compDefAccount: getCompDefAccAddress(
+        program.programId,
+        Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE()
+      ),
+      payer: payer.publicKey,
+    });
+
+    // Both sides escrow what a fill costs them; the buy escrows its 1% fee too
+    const submitEscrowedOrder = async (
+      isBuy: boolean,
+      escrowAmount: anchor.BN,
+      escrowSource: PublicKey,
+      escrowVault: PublicKey,
+      escrowMint: PublicKey,
+      referrer: PublicKey | null = null
+    ) => {
+      const offset = await nextComputationOffset(program, tradingPair);
+      const nonce = nextClientNonce();
+      const privateKey = x25519.utils.randomSecretKey();
+      const cipher = new RescueCipher(x25519.getSharedSecret(privateKey, mxePublicKey));
+      const ciphertext = cipher.encrypt(
+        [BigInt(100_000_000), BigInt(10_000_000_000), BigInt(isBuy ? 1 : 0)],
+        nonce
+      );
+      const submittedPromise = awaitEvent("orderSubmittedEvent");
+      await program.methods
+        .submitOrder(
+          offset,
+          tradingPairId,
+          Array.from(x25519.getPublicKey(privateKey)),
+          new anchor.BN(Buffer.from(nonce).toString("hex"), "hex"),
+          Array.from(ciphertext[0]),
+          Array.from(ciphertext[1]),
+          Array.from(ciphertext[2]),
+          escrowAmount
+        )
+        .accountsPartial({
+          tradingPair,
+          ...queueAccounts(offset, "submit_order"),
+          escrowSource,
+          escrowVault,
+          escrowMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          referrer,
+        })
+        .signers([payer])
+        .rpc({ skipPreflight: true, commitment: "confirmed" });
+      await awaitComputationFinalization(provider, offset, program.programId, "confirmed");
+      await submittedPromise;
+    };
+
+    // 10 tokens at 100 USDC: the resting sell is the maker and the later buy
+    // the taker, whose base fee is 10 USDC
+    await submitEscrowedOrder(
+      false,
+      new anchor.BN(10_000_000_000),
+      payerBaseAccount,
+      baseVault,
+      baseMint
+    );
+    await submitEscrowedOrder(
+      true,
+      new anchor.BN(1_010_000_000),
+      payerQuoteAccount,
+      quoteVault,
+      quoteMint
+    );
+
+    const matchOffset = await nextComputationOffset(program, tradingPair);
+    const matchRecordedPromise = awaitEvent("matchRecordedEvent");
+    // Match the book and settle the payer, who is on both sides
+    const matchAndSettle = async () => {
+      const matchOffset = await nextComputationOffset(program, tradingPair);
+      const matchRecordedPromise = awaitEvent("matchRecordedEvent");
+      await program.methods
+        .matchOrders(matchOffset, tradingPairId)
+        .accountsPartial({ tradingPair, ...queueAccounts(matchOffset, "match_orders") })
+        .signers([payer])
+        .rpc({ skipPreflight: true, commitment: "confirmed" });
+      await awaitComputationFinalization(provider, matchOffset, program.programId, "confirmed");
+      const matchRecorded = await matchRecordedPromise;
+      expect(matchRecorded.tradeCount).to.equal(1);
+
+      const settleOffset = await nextComputationOffset(program, tradingPair);
+      const fillReportPromise = awaitEvent("fillReportEvent");
+      await program.methods
+        .settleFills(settleOffset, tradingPairId, matchRecorded.sequence)
+        .accountsPartial({
+          tradingPair,
+          userAccount,
+          ...queueAccounts(settleOffset, "settle_fills"),
+        })
+        .signers([payer])
+        .rpc({ skipPreflight: true, commitment: "confirmed" });
+      await awaitComputationFinalization(provider, settleOffset, program.programId, "confirmed");
+      await fillReportPromise;
+    };
+    await matchAndSettle();
+
+    // The full base fee was charged. The top tier's share of it is held back
+    // in the quote vault, so the amount reveals nothing about the payer's tier.
+    // The orders had no referrer, so no referral share was carved out.
+    const settled = await program.account.userAccount.fetch(userAccount);
+    expect(settled.quoteEscrowed.toString()).to.equal("1000000000");
+    expect(settled.feeCreditReserve.toString()).to.equal("5000000");
+    const settledPair = await program.account.tradingPair.fetch(tradingPair);
+    expect(settledPair.feeCreditReserved.toString()).to.equal("5000000");
+    expect(settledPair.referralOwed.toString()).to.equal("0");
+    expect((await getAccount(provider.connection, feeVault)).amount.toString()).to.equal(
+      "5000000"
+    );
+
+    // Claiming pays the payer's own 20% discount into their free balance and
+    // sends the rest of the reserve to the fee vault
+    const claimOffset = await nextComputationOffset(program, tradingPair);
+    const claimedPromise = awaitEvent("feeCreditClaimedEvent");
+    await program.methods
+      .claimFeeCredit(claimOffset, tradingPairId)
+      .accountsPartial({
+        tradingPair,
+        userAccount,
+        ...queueAccounts(claimOffset, "claim_fee_credit"),
+      })
+      .signers([payer])
+      .rpc({ skipPreflight: true, commitment: "confirmed" });
+    await awaitComputationFinalization(provider, claimOffset, program.programId, "confirmed");
+    const claimed = await claimedPromise;
+    expect(claimed.owner.toBase58()).to.equal(payer.publicKey.toBase58());
+    expect(claimed.amount.toString()).to.equal("2000000");
+
+    const claimedAccount = await program.account.userAccount.fetch(userAccount);
+    expect(claimedAccount.quoteEscrowed.toString()).to.equal("1002000000");
+    expect(claimedAccount.feeCreditReserve.toString()).to.equal("0");
+    expect(
+      (await program.account.tradingPair.fetch(tradingPair)).feeCreditReserved.toString()
+    ).to.equal("0");
+    expect((await getAccount(provider.connection, feeVault)).amount.toString()).to.equal(
+      "8000000"
+    );
+
+    // Nothing is left to claim
+    const emptyOffset = await nextComputationOffset(program, tradingPair);
+    let claimedAgain = true;
+    try {
+      await program.methods
+        .claimFeeCredit(emptyOffset, tradingPairId)
+        .accountsPartial({
+          tradingPair,
+          userAccount,
+          ...queueAccounts(emptyOffset, "claim_fee_credit"),
+        })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+    } catch (error) {
+      claimedAgain = false;
+    }
+    expect(claimedAgain).to.equal(false);
+
+    // Two partner front-ends register as referrers on the pair
+    const partners = [Keypair.generate(), Keypair.generate()];
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        ...partners.map((partner) =>
+          SystemProgram.transfer({
+            fromPubkey: payer.publicKey,
+            toPubkey: partner.publicKey,
+            lamports: LAMPORTS_PER_SOL / 10,
+          })
+        )
+      ),
+      [payer]
+    );
+    const [referrerA, referrerB] = partners.map(
+      (partner) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("referrer"), tradingPair.toBuffer(), partner.publicKey.toBuffer()],
+          program.programId
+        )[0]
+    );
+    for (const partner of partners) {
+      await program.methods
+        .registerReferrer(tradingPairId)
+        .accountsPartial({ owner: partner.publicKey, tradingPair })
+        .signers([partner])
+        .rpc({ commitment: "confirmed" });
+    }
+
+    // An order placed through a partner sets the payer's referrer
+    await submitEscrowedOrder(
+      false,
+      new anchor.BN(10_000_000_000),
+      payerBaseAccount,
+      baseVault,
+      baseMint,
+      referrerA
+    );
+    expect((await program.account.userAccount.fetch(userAccount)).referrer.toBase58()).to.equal(
+      referrerA.toBase58()
+    );
+
+    // Another partner cannot take the referral over, and the referrer cannot
+    // be cleared while the referred order rests
+    let switched = true;
+    try {
+      await submitEscrowedOrder(
+        true,
+        new anchor.BN(1_010_000_000),
+        payerQuoteAccount,
+        quoteVault,
+        quoteMint,
+        referrerB
+      );
+    } catch (error) {
+      switched = false;
+    }
+    expect(switched).to.equal(false);
+    let cleared = true;
+    try {
+      await program.methods
+        .clearReferrer(tradingPairId)
+        .accountsPartial({ owner: payer.publicKey, tradingPair, userAccount })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+    } catch (error) {
+      cleared = false;
+    }
+    expect(cleared).to.equal(false);
+
+    // The referrer is kept for later orders: the referred buy takes the sell
+    // and a fifth of its 10 USDC fee accrues to the partner
+    await submitEscrowedOrder(
+      true,
+      new anchor.BN(1_010_000_000),
+      payerQuoteAccount,
+      quoteVault,
+      quoteMint
+    );
+    await matchAndSettle();
+    expect((await program.account.referrer.fetch(referrerA)).accrued.toString()).to.equal(
+      "2000000"
+    );
+    expect((await program.account.referrer.fetch(referrerB)).accrued.toString()).to.equal("0");
+    expect(
+      (await program.account.tradingPair.fetch(tradingPair)).referralOwed.toString()
+    ).to.equal("2000000");
+
+    // With nothing left open the payer can drop the referrer
+    await program.methods
+      .clearReferrer(tradingPairId)
+      .accountsPartial({ owner: payer.publicKey, tradingPair, userAccount })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    expect((await program.account.userAccount.fetch(userAccount)).referrer.toBase58()).to.equal(
+      PublicKey.default.toBase58()
+    );
+
+    // A cranker posts a bond with each match and loses it if the match finds
+    // nothing to trade
+    const matchBond = new anchor.BN(LAMPORTS_PER_SOL / 100);
+
+    // Rewards are capped at 10 whole quote tokens a match
+    let overpaid = true;
+    try {
+      await program.methods
+        .configureCrank(
+          tradingPairId,
+          new anchor.BN(1_000_000),
+          new anchor.BN(10_000_001),
+          matchBond
+        )
+        .accountsPartial({ authority: payer.publicKey, tradingPair })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+    } catch (error) {
+      overpaid = false;
+    }
+    expect(overpaid).to.equal(false);
+    await program.methods
+      .configureCrank(tradingPairId, new anchor.BN(1_000_000), new anchor.BN(0), matchBond)
+      .accountsPartial({ authority: payer.publicKey, tradingPair })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    const crankEmptyBook = async () => {
+      const offset = await nextComputationOffset(program, tradingPair);
+      const forfeitedPromise = awaitEvent("matchBondForfeitedEvent");
+      await program.methods
+        .matchOrders(offset, tradingPairId)
+        .accountsPartial({ tradingPair, ...queueAccounts(offset, "match_orders") })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+      await awaitComputationFinalization(provider, offset, program.programId, "confirmed");
+      const forfeited = await forfeitedPromise;
+      expect(forfeited.amount.toString()).to.equal(matchBond.toString());
+    };
+    await crankEmptyBook();
+
+    // The unchanged book cannot be matched again within the interval
+    const repeatOffset = await nextComputationOffset(program, tradingPair);
+    let rematched = true;
+    try {
+      await program.methods
+        .matchOrders(repeatOffset, tradingPairId)
+        .accountsPartial({ tradingPair, ...queueAccounts(repeatOffset, "match_orders") })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+    } catch (error) {
+      rematched = false;
+    }
+    expect(rematched).to.equal(false);
+
+    // but a new order changes the book, so it can be matched at once
+    await submitEscrowedOrder(
+      false,
+      new anchor.BN(10_000_000_000),
+      payerBaseAccount,
+      baseVault,
+      baseMint
+    );
+    await crankEmptyBook();
+
+    // A recurring session repeats every period from its first pre-open, so
+    // two periods on the pair is pre-open again and collects orders unmatched
+    const sessionSlot = await provider.connection.getSlot("confirmed");
+    const period = 10_000;
+    const firstPreOpen = sessionSlot - 2 * period;
+    await program.methods
+      .configureSession(tradingPairId, {
+        preOpenSlot: new anchor.BN(firstPreOpen),
+        openingAuctionSlot: new anchor.BN(firstPreOpen + period / 2),
+        continuousSlot: new anchor.BN(firstPreOpen + period / 2 + 100),
+        closingAuctionSlot: new anchor.BN(firstPreOpen + period / 2 + 200),
+        closedSlot: new anchor.BN(firstPreOpen + period / 2 + 300),
+        periodSlots: new anchor.BN(period),
+      })
+      .accountsPartial({ authority: payer.publicKey, tradingPair })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    const preOpenPair = await program.account.tradingPair.fetch(tradingPair);
+    expect(preOpenPair.sessionState).to.deep.equal({ preOpen: {} });
+    expect(preOpenPair.sessionStartedSlot.toString()).to.equal(sessionSlot.toString());
+
+    await submitEscrowedOrder(
+      true,
+      new anchor.BN(1_010_000_000),
+      payerQuoteAccount,
+      quoteVault,
+      quoteMint
+    );
+    const preOpenOffset = await nextComputationOffset(program, tradingPair);
+    let matchedPreOpen = true;
+    try {
+      await program.methods
+        .matchOrders(preOpenOffset, tradingPairId)
+        .accountsPartial({ tradingPair, ...queueAccounts(preOpenOffset, "match_orders") })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+    } catch (error) {
+      matchedPreOpen = false;
+    }
+    expect(matchedPreOpen).to.equal(false);
+
+    // The opening auction uncrosses the orders collected before it, once
+    const unscheduled = {
+      preOpenSlot: new anchor.BN(0),
+      openingAuctionSlot: new anchor.BN(0),
+      continuousSlot: new anchor.BN(0),
+      closingAuctionSlot: new anchor.BN(0),
+      closedSlot: new anchor.BN(0),
+      periodSlots: new anchor.BN(0),
+    };
+    await program.methods
+      .configureSession(tradingPairId, unscheduled)
+      .accountsPartial({ authority: payer.publicKey, tradingPair })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .setSessionState(tradingPairId, { openingAuction: {} })
+      .accountsPartial({ authority: payer.publicKey, tradingPair })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    await matchAndSettle();
+
+    const uncrossAgainOffset = await nextComputationOffset(program, tradingPair);
+    let uncrossedAgain = true;
+    try {
+      await program.methods
+        .matchOrders(uncrossAgainOffset, tradingPairId)
+        .accountsPartial({ tradingPair, ...queueAccounts(uncrossAgainOffset, "match_orders") })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+    } catch (error) {
+      uncrossedAgain = false;
+    }
+    expect(uncrossedAgain).to.equal(false);
+  });
+
+  // A market is addressed by its mints in byte order, so it and its inverse
+  // share one address
+  function tradingPairAddress(baseMint: PublicKey, quoteMint: PublicKey): PublicKey {
+    const seedMints = [baseMint.toBuffer(), quoteMint.toBuffer()].sort(Buffer.compare);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("trading_pair"), ...seedMints],
+      program.programId
+    )[0];
+  }
+
+  // Token-2022 mint with a 1% transfer fee, optionally a fee authority and
+  // optionally a close authority
+  async function createToken2022Mint(
+    payer: anchor.web3.Keypair,
+    feeAuthority: PublicKey | null,
+    closable: boolean
+  ): Promise<PublicKey> {
+    const mint = Keypair.generate();
+    const extensions = [ExtensionType.TransferFeeConfig];
+    if (closable) extensions.push(ExtensionType.MintCloseAuthority);
+    const space = getMintLen(extensions);
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint.publicKey,
+        space,
+        lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferFeeConfigInstruction(
+        mint.publicKey,
+        feeAuthority,
+        null,
+        100,
+        BigInt(1_000_000_000),
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    if (closable) {
+      tx.add(
+        createInitializeMintCloseAuthorityInstruction(
+          mint.publicKey,
+          payer.publicKey,
+          TOKEN_2022_PROGRAM_ID
+        )
+      );
+    }
+    tx.add(
+      createInitializeMintInstruction(mint.publicKey, 9, payer.publicKey, null, TOKEN_2022_PROGRAM_ID)
+    );
+    await provider.sendAndConfirm(tx, [payer, mint]);
+    return mint.publicKey;
+  }
+
+  // Queue a trading pair for two mints, private unless a policy is given, and
+  // return its computation offset
+  async function queueTradingPair(
+    payer: anchor.web3.Keypair,
+    tradingPairId: anchor.BN,
+    baseMint: PublicKey,
+    quoteMint: PublicKey,
+    policy: { private: {} } | { priceAndSize: {} } = { private: {} }
+  ): Promise<anchor.BN> {
+    const tradingPair = tradingPairAddress(baseMint, quoteMint);
+    const computationOffset = deriveComputationOffset(tradingPair, new anchor.BN(0));
+    await program.methods
+      .initializeTradingPair(
+        computationOffset,
+        tradingPairId,
+        new anchor.BN(randomBytes(16), "hex"),
+        policy,
+        {
+          tickSize: new anchor.BN(1_000),
+          lotSize: new anchor.BN(1_000),
+          minOrderSize: new anchor.BN(1_000_000),
+          priceScale: new anchor.BN(1_000_000_000),
+          maxOrderPrice: new anchor.BN(1_000_000_000_000),
+          maxOrderQuantity: new anchor.BN(1_000_000_000_000_000),
+        }
+      )
+      .accountsPartial({
+        tradingPair,
+        baseMint,
+        quoteMint,
+        computationAccount: getComputationAccAddress(program.programId, computationOffset),
+        clusterAccount: clusterAccount,
+        mxeAccount: getMXEAccAddress(program.programId),
+        mempoolAccount: getMempoolAccAddress(program.programId),
+        executingPool: getExecutingPoolAccAddress(program.programId),
+        compDefAccount: getCompDefAccAddress(
+          program.programId,
+          Buffer.from(getCompDefAccOffset("init_order_book")).readUInt32LE()
+        ),
+        payer: payer.publicKey,
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+    return computationOffset;
+  }
+
   async function initCompDef(
     program: Program<ConfHide>,
     owner: anchor.web3.Keypair,
@@ -564,6 +1627,35 @@ async function getMXEPublicKeyWithRetry(
   throw new Error(`Failed to fetch MXE public key after ${maxRetries} attempts`);
 }
 
+// Mirrors derive_computation_offset in the program
+function deriveComputationOffset(
+  tradingPair: PublicKey,
+  sequence: anchor.BN
+): anchor.BN {
+  const hash = createHash("sha256")
+    .update(Buffer.from("computation_offset"))
+    .update(tradingPair.toBuffer())
+    .update(sequence.toArrayLike(Buffer, "le", 8))
+    .digest();
+  return new anchor.BN(hash.subarray(0, 8), "le");
+}
+
+async function nextComputationOffset(
+  program: Program<ConfHide>,
+  tradingPair: PublicKey
+): Promise<anchor.BN> {
+  const pair = await program.account.tradingPair.fetch(tradingPair);
+  return deriveComputationOffset(tradingPair, pair.computationCount);
+}
+
+// Client nonces must strictly increase per trader, so prefix them with the current time
+function nextClientNonce(): Buffer {
+  const nonce = Buffer.alloc(16);
+  nonce.writeBigUInt64BE(BigInt(Date.now()), 0);
+  randomBytes(8).copy(nonce, 8);
+  return nonce;
+}
+
 function readKpJson(path: string): anchor.web3.Keypair {
   const file = fs.readFileSync(path);
   return anchor.web3.Keypair.fromSecretKey(new Uint8Array(JSON.parse(file.toString())));
@@ -228,6 +228,54 @@ describe("ConfHide - Privacy Trading Platform", () => {
       "initMatchOrdersCompDef"
     );
     console.log("Match orders comp def initialized:", initMatchOrdersSig);
+
+    const initQueryMyOrdersSig = await initCompDef(
+      program,
+      payer,
+      "query_my_orders",
+      "initQueryMyOrdersCompDef"
+    );
+    console.log("Query my orders comp def initialized:", initQueryMyOrdersSig);
+
+    const initRevealDepthSig = await initCompDef(
+      program,
+      payer,
+      "reveal_depth",
+      "initRevealDepthCompDef"
+    );
+    console.log("Reveal depth comp def initialized:", initRevealDepthSig);
+
+    const initIndicativePriceSig = await initCompDef(
+      program,
+      payer,
+      "indicative_price",
+      "initIndicativePriceCompDef"
+    );
+    console.log("Indicative price comp def initialized:", initIndicativePriceSig);
+
+    const initRevealTapeSig = await initCompDef(
+      program,
+      payer,
+      "reveal_tape",
+      "initRevealTapeCompDef"
+    );
+    console.log("Reveal tape comp def initialized:", initRevealTapeSig);
+
+    const initSettleFillsSig = await initCompDef(
+      program,
+      payer,
+      "settle_fills",
+      "initSettleFillsCompDef"
+    );
+    console.log("Settle fills comp def initialized:", initSettleFillsSig);
+
+    const initClaimFeeCreditSig = await initCompDef(
+      program,
+      payer,
+      "claim_fee_credit",
+      "initClaimFeeCreditCompDef"
+    );
+    console.log("Claim fee credit comp def initialized:", initClaimFeeCreditSig);
   });
 
   async function initCompDef(